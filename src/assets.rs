@@ -0,0 +1,115 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use image::RgbaImage;
+
+const PLACEHOLDER_SIZE: u32 = 16;
+const PLACEHOLDER_CHECK: u32 = 4;
+
+// Built-in copies of every asset the game uses. A file with the same name in
+// the override directory always wins over the embedded one.
+const EMBEDDED: &[(&str, &[u8])] = &[
+    ("bg.png", include_bytes!("../assets/bg.png")),
+    ("background.png", include_bytes!("../assets/background.png")),
+    ("base.png", include_bytes!("../assets/base.png")),
+    ("toppipe.png", include_bytes!("../assets/toppipe.png")),
+    ("bottompipe.png", include_bytes!("../assets/bottompipe.png")),
+    ("bullet.png", include_bytes!("../assets/bullet.png")),
+    ("alien.png", include_bytes!("../assets/alien.png")),
+    ("flappybird.png", include_bytes!("../assets/flappybird.png")),
+    ("flappybird.gif", include_bytes!("../assets/flappybird.gif")),
+    ("0.png", include_bytes!("../assets/0.png")),
+    ("1.png", include_bytes!("../assets/1.png")),
+    ("2.png", include_bytes!("../assets/2.png")),
+    ("3.png", include_bytes!("../assets/3.png")),
+    ("4.png", include_bytes!("../assets/4.png")),
+    ("5.png", include_bytes!("../assets/5.png")),
+    ("6.png", include_bytes!("../assets/6.png")),
+    ("7.png", include_bytes!("../assets/7.png")),
+    ("8.png", include_bytes!("../assets/8.png")),
+    ("9.png", include_bytes!("../assets/9.png")),
+    ("sprites.png", include_bytes!("../assets/sprites.png")),
+    ("sprites.toml", include_bytes!("../assets/sprites.toml")),
+];
+
+#[derive(Debug)]
+pub enum AssetError {
+    Unknown(String),
+    Io { name: String, source: io::Error },
+    Decode { name: String, source: image::ImageError },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Unknown(name) => write!(f, "no asset named {}", name),
+            AssetError::Io { name, source } => write!(f, "could not read asset {}: {}", name, source),
+            AssetError::Decode { name, source } => write!(f, "could not decode asset {}: {}", name, source),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+/// Finds asset files, checking the override directory before falling back
+/// to the copies compiled into the binary.
+#[derive(Debug, Clone, Default)]
+pub struct Assets {
+    override_dir: Option<PathBuf>,
+}
+
+impl Assets {
+    pub fn new(override_dir: Option<PathBuf>) -> Self {
+        Self { override_dir }
+    }
+
+    pub fn bytes(&self, name: &str) -> Result<Cow<'static, [u8]>, AssetError> {
+        if let Some(dir) = &self.override_dir {
+            let path = dir.join(name);
+            if path.is_file() {
+                return fs::read(&path)
+                    .map(Cow::Owned)
+                    .map_err(|source| AssetError::Io { name: name.to_string(), source });
+            }
+        }
+
+        Self::embedded_bytes(name).map(Cow::Borrowed)
+    }
+
+    pub fn embedded_bytes(name: &str) -> Result<&'static [u8], AssetError> {
+        EMBEDDED.iter()
+            .find(|(embedded_name, _)| *embedded_name == name)
+            .map(|(_, bytes)| *bytes)
+            .ok_or_else(|| AssetError::Unknown(name.to_string()))
+    }
+
+    pub fn load_image(&self, name: &str) -> Result<RgbaImage, AssetError> {
+        let bytes = self.bytes(name)?;
+        image::load_from_memory(&bytes)
+            .map(|img| img.to_rgba8())
+            .map_err(|source| AssetError::Decode { name: name.to_string(), source })
+    }
+
+    /// Like `load_image`, but reports the problem and hands back a
+    /// placeholder instead of failing.
+    pub fn image(&self, name: &str) -> RgbaImage {
+        self.load_image(name).unwrap_or_else(|err| {
+            eprintln!("{}, using a placeholder", err);
+            placeholder()
+        })
+    }
+}
+
+/// Magenta and black checkerboard, hard to miss on screen.
+pub fn placeholder() -> RgbaImage {
+    image::ImageBuffer::from_fn(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, |x, y| {
+        if (x / PLACEHOLDER_CHECK + y / PLACEHOLDER_CHECK).is_multiple_of(2) {
+            image::Rgba([255, 0, 255, 255])
+        } else {
+            image::Rgba([0, 0, 0, 255])
+        }
+    })
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use image::RgbaImage;
+use serde::Deserialize;
+
+use crate::assets::{AssetError, Assets};
+
+/// The sidecar descriptor of a sprite sheet, `sprites.toml` for
+/// `sprites.png`. Rectangles are `[x, y, width, height]` in pixels.
+///
+/// ```toml
+/// [sprites.toppipe]
+/// rect = [0, 0, 52, 320]
+///
+/// # Frames listed one by one...
+/// [animations.bird]
+/// frames = [[0, 400, 34, 24], [34, 400, 34, 24]]
+/// fps = 12.0
+///
+/// # ...or as a row of equal cells, left to right.
+/// [animations.walk]
+/// start = [96, 199]
+/// size = [78, 70]
+/// count = 8
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Descriptor {
+    sprites: HashMap<String, SpriteEntry>,
+    animations: HashMap<String, AnimationEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpriteEntry {
+    rect: [u32; 4],
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AnimationEntry {
+    #[serde(default)]
+    frames: Vec<[u32; 4]>,
+    start: Option<[u32; 2]>,
+    size: Option<[u32; 2]>,
+    #[serde(default)]
+    count: u32,
+    #[serde(default = "default_fps")]
+    fps: f32,
+}
+
+fn default_fps() -> f32 {
+    12.0
+}
+
+impl AnimationEntry {
+    /// Every frame's rectangle, None for a cell whose x doesn't fit in a
+    /// u32. Cells are worked out one at a time, so a huge `count` fails on
+    /// the first cell past the sheet instead of being collected first.
+    fn rects(&self) -> impl Iterator<Item = Option<[u32; 4]>> + '_ {
+        let row = self.start.zip(self.size);
+        let count = if row.is_some() { self.count } else { 0 };
+        let cells = (0..count).map(move |idx| {
+            let ([x, y], [w, h]) = row?;
+            Some([idx.checked_mul(w)?.checked_add(x)?, y, w, h])
+        });
+        self.frames.iter().copied().map(Some).chain(cells)
+    }
+}
+
+#[derive(Debug)]
+pub enum AtlasError {
+    Asset(AssetError),
+    Descriptor { name: String, source: toml::de::Error },
+    OutOfBounds { sheet: String, entry: String },
+    NoFrames { sheet: String, entry: String },
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtlasError::Asset(err) => write!(f, "{}", err),
+            AtlasError::Descriptor { name, source } => write!(f, "could not parse sprite sheet descriptor {}: {}", name, source),
+            AtlasError::OutOfBounds { sheet, entry } => write!(f, "{} in {} reaches outside the sheet", entry, sheet),
+            AtlasError::NoFrames { sheet, entry } => write!(f, "animation {} in {} has no frames", entry, sheet),
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
+impl From<AssetError> for AtlasError {
+    fn from(err: AssetError) -> Self {
+        AtlasError::Asset(err)
+    }
+}
+
+/// Frames cut from a sheet, played in order at `fps`.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub frames: Vec<RgbaImage>,
+    pub fps: f32,
+}
+
+/// Named sprites and animations sliced out of one sprite sheet.
+#[derive(Debug, Clone, Default)]
+pub struct Atlas {
+    sprites: HashMap<String, RgbaImage>,
+    animations: HashMap<String, Animation>,
+}
+
+impl Atlas {
+    /// Loads `sheet` and slices it with the descriptor next to it. Both go
+    /// through `assets`, so either can be overridden.
+    pub fn load(assets: &Assets, sheet: &str) -> Result<Self, AtlasError> {
+        let descriptor_name = descriptor_name(sheet);
+        let bytes = assets.bytes(&descriptor_name)?;
+        let text = String::from_utf8_lossy(&bytes);
+        let descriptor: Descriptor = toml::from_str(&text)
+            .map_err(|source| AtlasError::Descriptor { name: descriptor_name, source })?;
+
+        let image = assets.load_image(sheet)?;
+        let out_of_bounds = |entry: &str| AtlasError::OutOfBounds { sheet: sheet.to_string(), entry: entry.to_string() };
+        let cut = |entry: &str, rect: Option<[u32; 4]>| {
+            let [x, y, w, h] = rect.ok_or_else(|| out_of_bounds(entry))?;
+            let fits = |start: u32, len: u32, max: u32| start.checked_add(len).is_some_and(|end| end <= max);
+            if w == 0 || h == 0 || !fits(x, w, image.width()) || !fits(y, h, image.height()) {
+                return Err(out_of_bounds(entry));
+            }
+            Ok(image::imageops::crop_imm(&image, x, y, w, h).to_image())
+        };
+
+        let mut atlas = Atlas::default();
+        for (name, entry) in &descriptor.sprites {
+            atlas.sprites.insert(name.clone(), cut(name, Some(entry.rect))?);
+        }
+        for (name, entry) in &descriptor.animations {
+            let frames = entry.rects()
+                .map(|rect| cut(name, rect))
+                .collect::<Result<Vec<_>, _>>()?;
+            if frames.is_empty() {
+                return Err(AtlasError::NoFrames { sheet: sheet.to_string(), entry: name.clone() });
+            }
+            atlas.animations.insert(name.clone(), Animation { frames, fps: entry.fps });
+        }
+
+        Ok(atlas)
+    }
+
+    /// A sprite by name. An animation with that name stands in with its
+    /// first frame.
+    pub fn sprite(&self, name: &str) -> Option<&RgbaImage> {
+        self.sprites.get(name).or_else(|| self.animations.get(name).and_then(|animation| animation.frames.first()))
+    }
+
+    pub fn animation(&self, name: &str) -> Option<&Animation> {
+        self.animations.get(name)
+    }
+}
+
+/// `sprites.png` -> `sprites.toml`.
+fn descriptor_name(sheet: &str) -> String {
+    let stem = sheet.rsplit_once('.').map_or(sheet, |(stem, _)| stem);
+    format!("{}.toml", stem)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// A 64x64 sheet with the given descriptor, loaded through an override
+    /// directory.
+    fn load_with(descriptor: &str) -> Result<Atlas, AtlasError> {
+        let dir = tempfile::tempdir().unwrap();
+        RgbaImage::new(64, 64).save(dir.path().join("test.png")).unwrap();
+        fs::write(dir.path().join("test.toml"), descriptor).unwrap();
+        Atlas::load(&Assets::new(Some(dir.path().to_path_buf())), "test.png")
+    }
+
+    #[test]
+    fn shipped_sheet_has_every_name_the_renderer_reads() {
+        let atlas = Atlas::load(&Assets::new(None), "sprites.png").unwrap();
+        for name in ["toppipe", "bottompipe", "ground"] {
+            assert!(atlas.sprite(name).is_some(), "{} is missing", name);
+        }
+        for digit in 0..10 {
+            assert!(atlas.sprite(&format!("digit_{}", digit)).is_some());
+        }
+        assert!(atlas.animation("bird").is_some_and(|bird| bird.frames.len() > 1));
+    }
+
+    #[test]
+    fn cuts_rows_of_cells() {
+        let atlas = load_with("[animations.bird]\nstart = [8, 0]\nsize = [16, 10]\ncount = 3\n").unwrap();
+        let bird = atlas.animation("bird").unwrap();
+        assert_eq!(bird.frames.len(), 3);
+        assert_eq!(bird.frames[0].dimensions(), (16, 10));
+    }
+
+    #[test]
+    fn rects_past_the_sheet_are_errors() {
+        assert!(matches!(load_with("[sprites.a]\nrect = [60, 0, 8, 8]\n"), Err(AtlasError::OutOfBounds { .. })));
+        assert!(matches!(load_with("[animations.a]\nstart = [0, 0]\nsize = [16, 16]\ncount = 5\n"), Err(AtlasError::OutOfBounds { .. })));
+        assert!(matches!(load_with("[animations.a]\n"), Err(AtlasError::NoFrames { .. })));
+    }
+
+    #[test]
+    fn overflowing_rects_are_errors() {
+        let max = u32::MAX;
+        let huge_sprite = format!("[sprites.a]\nrect = [{}, {}, 8, 8]\n", max, max);
+        assert!(matches!(load_with(&huge_sprite), Err(AtlasError::OutOfBounds { .. })));
+
+        let huge_row = format!("[animations.a]\nstart = [{}, 0]\nsize = [{}, 8]\ncount = {}\n", max, max, max);
+        assert!(matches!(load_with(&huge_row), Err(AtlasError::OutOfBounds { .. })));
+
+        let long_row = format!("[animations.a]\nstart = [0, 0]\nsize = [1, 1]\ncount = {}\n", max);
+        assert!(matches!(load_with(&long_row), Err(AtlasError::OutOfBounds { .. })));
+    }
+}
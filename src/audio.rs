@@ -0,0 +1,462 @@
+use std::f32::consts::TAU;
+use std::fmt;
+
+use rodio::buffer::SamplesBuffer;
+use rodio::{OutputStream, OutputStreamBuilder, Sink, Source};
+
+use crate::config::AudioConfig;
+use crate::timestep::TICK_RATE;
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+/// Bass, arpeggio and drums, faded in one after another as the score climbs.
+pub const MUSIC_STEMS: usize = 3;
+const MUSIC_BPM: f32 = 120.0;
+/// Am, F, C, G, one bar each, as the three notes of every chord.
+const MUSIC_CHORDS: [[f32; 3]; 4] = [
+    [220.0, 261.63, 329.63],
+    [174.61, 220.0, 261.63],
+    [261.63, 329.63, 392.0],
+    [196.0, 246.94, 293.66],
+];
+/// Seconds a stem takes to fade fully in or out.
+const MUSIC_FADE: f32 = 0.6;
+/// Share of the music volume left while the game is paused.
+const MUSIC_DUCK: f32 = 0.3;
+const STING_FADE_IN: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sound {
+    Flap,
+    Point,
+    Hit,
+    Die,
+    Coin,
+    PowerUp,
+    Shoot,
+    Explode,
+}
+
+/// What the music should be doing, picked by `Game` from its screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MusicCue {
+    Silent,
+    Intro,
+    Playing { score: usize },
+    Ducked { score: usize },
+    Ending,
+}
+
+/// Somewhere mono samples at `SAMPLE_RATE` can be sent. Volumes are final,
+/// master, effect and music volume are already folded into them.
+pub trait AudioBackend: fmt::Debug {
+    fn play(&mut self, samples: &[f32], volume: f32);
+    /// Loops every stem in lockstep, starting paused and silent.
+    fn load_music(&mut self, stems: &[Vec<f32>]);
+    fn set_stem_volume(&mut self, stem: usize, volume: f32);
+    fn set_music_playing(&mut self, playing: bool);
+}
+
+/// Plays through the default output device. Each music stem gets its own
+/// sink so it can be faded on its own.
+pub struct RodioBackend {
+    stream: OutputStream,
+    music: Vec<Sink>,
+}
+
+impl RodioBackend {
+    pub fn open() -> Result<Self, rodio::StreamError> {
+        let mut stream = OutputStreamBuilder::open_default_stream()?;
+        stream.log_on_drop(false);
+        Ok(Self { stream, music: Vec::new() })
+    }
+}
+
+impl fmt::Debug for RodioBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RodioBackend").finish_non_exhaustive()
+    }
+}
+
+impl AudioBackend for RodioBackend {
+    fn play(&mut self, samples: &[f32], volume: f32) {
+        let source = SamplesBuffer::new(1, SAMPLE_RATE, samples.to_vec()).amplify(volume);
+        self.stream.mixer().add(source);
+    }
+
+    fn load_music(&mut self, stems: &[Vec<f32>]) {
+        self.music = stems.iter().map(|stem| {
+            let sink = Sink::connect_new(self.stream.mixer());
+            sink.pause();
+            sink.set_volume(0.0);
+            sink.append(SamplesBuffer::new(1, SAMPLE_RATE, stem.clone()).repeat_infinite());
+            sink
+        }).collect();
+    }
+
+    fn set_stem_volume(&mut self, stem: usize, volume: f32) {
+        if let Some(sink) = self.music.get(stem) {
+            sink.set_volume(volume);
+        }
+    }
+
+    fn set_music_playing(&mut self, playing: bool) {
+        for sink in &self.music {
+            if playing {
+                sink.play();
+            } else {
+                sink.pause();
+            }
+        }
+    }
+}
+
+/// Drops every sound, for machines without an audio device and for running
+/// the game headless.
+#[derive(Debug, Default)]
+pub struct NullBackend;
+
+impl AudioBackend for NullBackend {
+    fn play(&mut self, _samples: &[f32], _volume: f32) {}
+    fn load_music(&mut self, _stems: &[Vec<f32>]) {}
+    fn set_stem_volume(&mut self, _stem: usize, _volume: f32) {}
+    fn set_music_playing(&mut self, _playing: bool) {}
+}
+
+/// The game's sound effects and music, synthesized once up front, and the
+/// volume settings they are played at.
+#[derive(Debug)]
+pub struct Audio {
+    backend: Box<dyn AudioBackend>,
+    config: AudioConfig,
+    flap: Vec<f32>,
+    point: Vec<f32>,
+    hit: Vec<f32>,
+    die: Vec<f32>,
+    coin: Vec<f32>,
+    power_up: Vec<f32>,
+    shoot: Vec<f32>,
+    explode: Vec<f32>,
+    sting: Vec<f32>,
+    /// Current level of every stem from 0 to 1, before any volume setting.
+    stem_levels: [f32; MUSIC_STEMS],
+    music_playing: bool,
+}
+
+impl Audio {
+    /// Opens the default output device, falling back to `NullBackend` with
+    /// a warning when there isn't one.
+    pub fn new(config: AudioConfig) -> Self {
+        match RodioBackend::open() {
+            Ok(backend) => Self::with_backend(Box::new(backend), config),
+            Err(err) => {
+                eprintln!("No audio output, playing without sound: {}", err);
+                Self::with_backend(Box::new(NullBackend), config)
+            }
+        }
+    }
+
+    pub fn with_backend(mut backend: Box<dyn AudioBackend>, config: AudioConfig) -> Self {
+        backend.load_music(&music_stems());
+
+        Self {
+            backend,
+            config,
+            flap: sweep(0.09, 380.0, 760.0),
+            point: [tone(0.08, 988.0), tone(0.18, 1319.0)].concat(),
+            hit: noise(0.12),
+            die: sweep(0.5, 620.0, 140.0),
+            coin: [tone(0.05, 1568.0), tone(0.15, 2093.0)].concat(),
+            power_up: [sweep(0.12, 440.0, 880.0), sweep(0.2, 660.0, 1320.0)].concat(),
+            shoot: sweep(0.08, 1400.0, 350.0),
+            explode: noise(0.35),
+            sting: sting(),
+            stem_levels: [0.0; MUSIC_STEMS],
+            music_playing: false,
+        }
+    }
+
+    pub fn play(&mut self, sound: Sound) {
+        let volume = self.effect_volume();
+        if volume <= 0.0 {
+            return;
+        }
+
+        let samples = match sound {
+            Sound::Flap => &self.flap,
+            Sound::Point => &self.point,
+            Sound::Hit => &self.hit,
+            Sound::Die => &self.die,
+            Sound::Coin => &self.coin,
+            Sound::PowerUp => &self.power_up,
+            Sound::Shoot => &self.shoot,
+            Sound::Explode => &self.explode,
+        };
+        self.backend.play(samples, volume);
+    }
+
+    /// The game-over sting. It fades in on its own while `MusicCue::Ending`
+    /// fades the loop out, so the two crossfade.
+    pub fn play_sting(&mut self) {
+        let volume = self.music_volume();
+        if volume > 0.0 {
+            self.backend.play(&self.sting, volume);
+        }
+    }
+
+    /// Moves every stem one tick closer to the level `cue` asks for. The
+    /// loop is paused once everything has faded out, and only then, so it
+    /// never keeps running under a screen that wants silence.
+    pub fn update_music(&mut self, cue: MusicCue) {
+        let targets = self.stem_targets(cue);
+        let step = 1.0 / (MUSIC_FADE * TICK_RATE);
+
+        for (level, target) in self.stem_levels.iter_mut().zip(targets) {
+            *level = if *level < target {
+                (*level + step).min(target)
+            } else {
+                (*level - step).max(target)
+            };
+        }
+
+        let audible = self.stem_levels.iter().any(|level| *level > 0.0);
+        if audible != self.music_playing {
+            self.music_playing = audible;
+            self.backend.set_music_playing(audible);
+        }
+
+        let volume = self.music_volume();
+        for (stem, level) in self.stem_levels.iter().enumerate() {
+            self.backend.set_stem_volume(stem, level * volume);
+        }
+    }
+
+    fn stem_targets(&self, cue: MusicCue) -> [f32; MUSIC_STEMS] {
+        let by_score = |score: usize| {
+            let mut levels = [1.0; MUSIC_STEMS];
+            for (level, threshold) in levels[1..].iter_mut().zip(self.config.intensity_scores) {
+                *level = if score >= threshold { 1.0 } else { 0.0 };
+            }
+            levels
+        };
+
+        match cue {
+            MusicCue::Silent | MusicCue::Ending => [0.0; MUSIC_STEMS],
+            MusicCue::Intro => by_score(0),
+            MusicCue::Playing { score } => by_score(score),
+            MusicCue::Ducked { score } => by_score(score).map(|level| level * MUSIC_DUCK),
+        }
+    }
+
+    fn music_volume(&self) -> f32 {
+        if self.config.muted {
+            0.0
+        } else {
+            self.config.master_volume * self.config.music_volume
+        }
+    }
+
+    pub fn effect_volume(&self) -> f32 {
+        if self.config.muted {
+            0.0
+        } else {
+            self.config.master_volume * self.config.sfx_volume
+        }
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.config.muted = muted;
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.config.muted
+    }
+}
+
+fn sample_count(seconds: f32) -> usize {
+    (seconds * SAMPLE_RATE as f32) as usize
+}
+
+/// Fades out linearly over the whole sound so nothing ends on a click.
+fn envelope(idx: usize, len: usize) -> f32 {
+    1.0 - idx as f32 / len as f32
+}
+
+/// Adds `samples` into `into` starting at `start`, cutting off whatever
+/// runs past the end.
+fn mix(into: &mut [f32], start: usize, samples: &[f32], gain: f32) {
+    for (out, sample) in into[start..].iter_mut().zip(samples) {
+        *out += sample * gain;
+    }
+}
+
+/// One four bar loop per stem. Every stem has the same length so they stay
+/// in step while looping.
+fn music_stems() -> Vec<Vec<f32>> {
+    let eighth = 30.0 / MUSIC_BPM;
+    let len = sample_count(eighth * 8.0 * MUSIC_CHORDS.len() as f32);
+    let mut stems = vec![vec![0.0; len]; MUSIC_STEMS];
+
+    let kick = sweep(0.15, 150.0, 45.0);
+    let hat = noise(0.03);
+
+    for (bar, chord) in MUSIC_CHORDS.iter().enumerate() {
+        for step in 0..8 {
+            let start = sample_count((bar * 8 + step) as f32 * eighth);
+
+            if step % 2 == 0 {
+                mix(&mut stems[0], start, &tone(eighth * 2.0, chord[0] / 2.0), 0.5);
+            }
+            mix(&mut stems[1], start, &tone(eighth, chord[step % 3] * 2.0), 0.25);
+            if step % 4 == 0 {
+                mix(&mut stems[2], start, &kick, 0.8);
+            }
+            mix(&mut stems[2], start, &hat, 0.3);
+        }
+    }
+
+    stems
+}
+
+/// The chord tones falling away, faded in over `STING_FADE_IN`.
+fn sting() -> Vec<f32> {
+    let mut samples = [392.0, 329.63, 261.63, 196.0].iter()
+        .flat_map(|freq| tone(0.25, *freq))
+        .collect::<Vec<f32>>();
+
+    let fade_in = sample_count(STING_FADE_IN);
+    for (idx, sample) in samples.iter_mut().take(fade_in).enumerate() {
+        *sample *= idx as f32 / fade_in as f32;
+    }
+    samples
+}
+
+fn tone(seconds: f32, freq: f32) -> Vec<f32> {
+    sweep(seconds, freq, freq)
+}
+
+/// A square-ish wave gliding from `from` to `to` Hz.
+fn sweep(seconds: f32, from: f32, to: f32) -> Vec<f32> {
+    let len = sample_count(seconds);
+    let mut phase = 0.0_f32;
+
+    (0..len).map(|idx| {
+        let progress = idx as f32 / len as f32;
+        let freq = from + (to - from) * progress;
+        phase = (phase + freq / SAMPLE_RATE as f32).fract();
+        let wave = (phase * TAU).sin();
+        wave.signum() * wave.abs().sqrt() * 0.5 * envelope(idx, len)
+    }).collect()
+}
+
+/// White noise from a fixed xorshift seed, so the sound is the same every
+/// time and the game's own random numbers are left alone.
+fn noise(seconds: f32) -> Vec<f32> {
+    let len = sample_count(seconds);
+    let mut state = 0x2545_f491_u32;
+
+    (0..len).map(|idx| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let sample = state as f32 / u32::MAX as f32 * 2.0 - 1.0;
+        sample * 0.6 * envelope(idx, len)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Enough ticks for any fade to finish.
+    const FADE_TICKS: usize = (MUSIC_FADE * TICK_RATE) as usize + 1;
+
+    fn silent_audio() -> Audio {
+        Audio::with_backend(Box::new(NullBackend), AudioConfig::default())
+    }
+
+    /// Length and volume of every sound played, shared with the test.
+    type Played = Rc<RefCell<Vec<(usize, f32)>>>;
+
+    /// Keeps what `NullBackend` drops.
+    #[derive(Debug, Default)]
+    struct Recorder {
+        played: Played,
+    }
+
+    impl AudioBackend for Recorder {
+        fn play(&mut self, samples: &[f32], volume: f32) {
+            self.played.borrow_mut().push((samples.len(), volume));
+        }
+        fn load_music(&mut self, _stems: &[Vec<f32>]) {}
+        fn set_stem_volume(&mut self, _stem: usize, _volume: f32) {}
+        fn set_music_playing(&mut self, _playing: bool) {}
+    }
+
+    fn recorded_audio() -> (Audio, Played) {
+        let recorder = Recorder::default();
+        let played = recorder.played.clone();
+        (Audio::with_backend(Box::new(recorder), AudioConfig::default()), played)
+    }
+
+    #[test]
+    fn muting_silences_effects_and_music() {
+        let mut audio = silent_audio();
+        assert!(audio.effect_volume() > 0.0);
+
+        audio.set_muted(!audio.is_muted());
+        assert!(audio.is_muted());
+        assert_eq!(audio.effect_volume(), 0.0);
+        assert_eq!(audio.music_volume(), 0.0);
+
+        audio.set_muted(!audio.is_muted());
+        assert!(!audio.is_muted());
+        assert!(audio.effect_volume() > 0.0);
+    }
+
+    #[test]
+    fn stems_join_as_the_score_climbs() {
+        let audio = silent_audio();
+        let [second, third] = audio.config.intensity_scores;
+
+        assert_eq!(audio.stem_targets(MusicCue::Playing { score: 0 }), [1.0, 0.0, 0.0]);
+        assert_eq!(audio.stem_targets(MusicCue::Playing { score: second }), [1.0, 1.0, 0.0]);
+        assert_eq!(audio.stem_targets(MusicCue::Playing { score: third }), [1.0, 1.0, 1.0]);
+        assert_eq!(audio.stem_targets(MusicCue::Ducked { score: third }), [MUSIC_DUCK; MUSIC_STEMS]);
+        assert_eq!(audio.stem_targets(MusicCue::Ending), [0.0; MUSIC_STEMS]);
+    }
+
+    #[test]
+    fn music_fades_in_and_pauses_once_silent() {
+        let mut audio = silent_audio();
+        audio.update_music(MusicCue::Intro);
+        assert!(audio.music_playing);
+        assert!(audio.stem_levels[0] < 1.0);
+
+        for _ in 0..FADE_TICKS {
+            audio.update_music(MusicCue::Intro);
+        }
+        assert_eq!(audio.stem_levels, [1.0, 0.0, 0.0]);
+
+        for _ in 0..FADE_TICKS {
+            audio.update_music(MusicCue::Silent);
+        }
+        assert_eq!(audio.stem_levels, [0.0; MUSIC_STEMS]);
+        assert!(!audio.music_playing);
+    }
+
+    #[test]
+    fn sting_plays_at_the_music_volume_unless_muted() {
+        let (mut audio, played) = recorded_audio();
+        audio.play_sting();
+        assert_eq!(*played.borrow(), vec![(audio.sting.len(), audio.music_volume())]);
+
+        audio.set_muted(true);
+        audio.play_sting();
+        audio.play(Sound::Flap);
+        assert_eq!(played.borrow().len(), 1);
+    }
+}
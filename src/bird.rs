@@ -0,0 +1,168 @@
+use crate::collision::{Obb, Rect};
+use crate::config::{PhysicsConfig, TiltConfig};
+use crate::timestep::TICK_RATE;
+
+#[derive(Debug, Clone)]
+pub struct Bird {
+    pub position: (f32, f32),
+    pub velocity: f32,
+    pub size: (f32, f32),
+    /// Degrees the bird is turned by, negative noses up.
+    pub angle: f32,
+    /// Ticks the wings keep beating faster after a flap.
+    fast_wing_ticks: u64,
+    physics: PhysicsConfig,
+    tilt: TiltConfig,
+}
+
+impl Bird {
+    pub fn new(position: (f32, f32), size: (f32, f32), physics: PhysicsConfig, tilt: TiltConfig) -> Self {
+        Self {
+            position,
+            velocity: 0.0,
+            size,
+            angle: 0.0,
+            fast_wing_ticks: 0,
+            physics,
+            tilt,
+        }
+    }
+
+    /// A flap always gives the same lift, so mashing doesn't launch the
+    /// bird off the top.
+    pub fn flap(&mut self) {
+        self.velocity = self.physics.flap_impulse;
+        self.fast_wing_ticks = (self.tilt.fast_wings * TICK_RATE) as u64;
+    }
+
+    pub fn update(&mut self) {
+        self.velocity = (self.velocity + self.physics.gravity).min(self.physics.max_fall_speed);
+        self.position.1 += self.velocity;
+        self.angle += (self.target_angle() - self.angle) * self.tilt.smoothing;
+        self.fast_wing_ticks = self.fast_wing_ticks.saturating_sub(1);
+    }
+
+    /// Where the velocity points the bird, from `max_up` at the flap speed
+    /// to `max_down` at the fastest fall. It eases in, so the nose only
+    /// drops once the bird is well into its fall.
+    fn target_angle(&self) -> f32 {
+        let range = self.physics.max_fall_speed - self.physics.flap_impulse;
+        let fall = ((self.velocity - self.physics.flap_impulse) / range).clamp(0.0, 1.0);
+        self.tilt.max_up + (self.tilt.max_down - self.tilt.max_up) * fall * fall
+    }
+
+    /// Holds the bird level and still, for hovering before a run.
+    pub fn level(&mut self) {
+        self.velocity = 0.0;
+        self.angle = 0.0;
+        self.fast_wing_ticks = 0;
+    }
+
+    /// How many times faster than normal the wings beat right now.
+    pub fn wing_rate(&self) -> f32 {
+        if self.fast_wing_ticks > 0 { self.tilt.fast_wing_rate } else { 1.0 }
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (self.position.0 + self.size.0 / 2.0, self.position.1 + self.size.1 / 2.0)
+    }
+
+    pub fn center_x(&self) -> f32 {
+        self.position.0 + self.size.0 / 2.0
+    }
+
+    /// Turns with the sprite, so a diving bird is tall and thin.
+    pub fn hitbox(&self) -> Obb {
+        let rect = Rect::new(self.position.0, self.position.1, self.size.0, self.size.1);
+        Obb::new(rect, self.angle.to_radians()).scaled(self.physics.hitbox_scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bird(tilt: TiltConfig) -> Bird {
+        Bird::new((200.0, 300.0), (50.0, 35.0), PhysicsConfig::default(), tilt)
+    }
+
+    #[test]
+    fn angle_stays_between_max_up_and_max_down() {
+        let tilt = TiltConfig::default();
+        let mut bird = bird(tilt);
+
+        for _ in 0..300 {
+            bird.update();
+            assert!(bird.angle <= tilt.max_down);
+        }
+        assert!(bird.angle > tilt.max_down - 0.5, "a long fall ends nose down, got {}", bird.angle);
+
+        for _ in 0..300 {
+            bird.flap();
+            bird.update();
+            assert!(bird.angle >= tilt.max_up);
+        }
+        assert!(bird.angle < tilt.max_up + 1.0, "flapping keeps the nose up, got {}", bird.angle);
+    }
+
+    #[test]
+    fn smoothing_eases_towards_the_target() {
+        let physics = PhysicsConfig::default();
+        let mut bird = bird(TiltConfig::default());
+        bird.flap();
+        bird.update();
+        let target = bird.target_angle();
+        assert_eq!(bird.angle, target * 0.2);
+
+        let mut snappy = Bird::new((200.0, 300.0), (50.0, 35.0), physics, TiltConfig { smoothing: 1.0, ..TiltConfig::default() });
+        snappy.flap();
+        snappy.update();
+        assert_eq!(snappy.angle, target);
+    }
+
+    #[test]
+    fn nose_only_drops_well_into_the_fall() {
+        let physics = PhysicsConfig::default();
+        let tilt = TiltConfig::default();
+        let mut bird = bird(tilt);
+
+        bird.velocity = physics.flap_impulse;
+        assert_eq!(bird.target_angle(), tilt.max_up);
+        bird.velocity = (physics.flap_impulse + physics.max_fall_speed) / 2.0;
+        assert!(bird.target_angle() < (tilt.max_up + tilt.max_down) / 2.0);
+        bird.velocity = physics.max_fall_speed;
+        assert_eq!(bird.target_angle(), tilt.max_down);
+    }
+
+    #[test]
+    fn wings_beat_faster_right_after_a_flap() {
+        let tilt = TiltConfig::default();
+        let mut bird = bird(tilt);
+        assert_eq!(bird.wing_rate(), 1.0);
+
+        bird.flap();
+        let fast_ticks = (tilt.fast_wings * TICK_RATE) as u64;
+        for _ in 0..fast_ticks {
+            assert_eq!(bird.wing_rate(), tilt.fast_wing_rate);
+            bird.update();
+        }
+        assert_eq!(bird.wing_rate(), 1.0);
+
+        bird.flap();
+        bird.level();
+        assert_eq!(bird.wing_rate(), 1.0);
+        assert_eq!(bird.angle, 0.0);
+    }
+
+    #[test]
+    fn hitbox_turns_with_the_bird() {
+        let mut bird = bird(TiltConfig::default());
+        let level = bird.hitbox().bounds();
+        assert!(level.w > level.h);
+
+        bird.angle = 90.0;
+        let diving = bird.hitbox().bounds();
+        assert!(diving.h > diving.w);
+        assert_eq!(bird.hitbox().center, bird.center());
+    }
+}
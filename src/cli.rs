@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+
+use crate::mode::GameMode;
+use crate::paths;
+
+const DEFAULT_RECORD_PATH: &str = "last_run.replay";
+const DEFAULT_CONFIG_PATH: &str = "rampy.toml";
+
+/// Options read from the command line at startup. Anything we don't
+/// recognise is reported and skipped rather than aborting the launch.
+#[derive(Debug, Clone)]
+pub struct LaunchOptions {
+    pub seed: Option<u64>,
+    pub mode: Option<GameMode>,
+    pub replay: Option<PathBuf>,
+    pub record: PathBuf,
+    pub config: PathBuf,
+    pub assets: Option<PathBuf>,
+}
+
+impl Default for LaunchOptions {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            mode: None,
+            replay: None,
+            record: paths::data_file(DEFAULT_RECORD_PATH),
+            config: paths::data_file(DEFAULT_CONFIG_PATH),
+            assets: paths::default_asset_dir(),
+        }
+    }
+}
+
+impl LaunchOptions {
+    pub fn from_env() -> Self {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = args.next().unwrap_or_default();
+                    match value.parse() {
+                        Ok(seed) => options.seed = Some(seed),
+                        Err(_) => eprintln!("Ignoring invalid --seed value '{}'", value),
+                    }
+                }
+                "--mode" => {
+                    let value = args.next().unwrap_or_default();
+                    match GameMode::from_name(&value) {
+                        Some(mode) => options.mode = Some(mode),
+                        None => eprintln!("Ignoring unknown --mode '{}'", value),
+                    }
+                }
+                "--replay" => match args.next() {
+                    Some(path) => options.replay = Some(PathBuf::from(path)),
+                    None => eprintln!("Ignoring --replay without a path"),
+                },
+                "--record" => match args.next() {
+                    Some(path) => options.record = PathBuf::from(path),
+                    None => eprintln!("Ignoring --record without a path"),
+                },
+                "--config" => match args.next() {
+                    Some(path) => options.config = PathBuf::from(path),
+                    None => eprintln!("Ignoring --config without a path"),
+                },
+                "--assets" => match args.next() {
+                    Some(path) => options.assets = Some(PathBuf::from(path)),
+                    None => eprintln!("Ignoring --assets without a path"),
+                },
+                other => eprintln!("Ignoring unknown argument '{}'", other),
+            }
+        }
+
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> LaunchOptions {
+        LaunchOptions::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn default_files_live_in_the_data_dir() {
+        let options = parse(&[]);
+        assert_eq!(options.record, paths::data_file(DEFAULT_RECORD_PATH));
+        assert_eq!(options.config, paths::data_file(DEFAULT_CONFIG_PATH));
+        if let Some(dir) = paths::data_dir() {
+            assert!(options.record.starts_with(&dir));
+            assert!(options.config.starts_with(&dir));
+        }
+    }
+
+    #[test]
+    fn paths_given_on_the_command_line_are_kept() {
+        let options = parse(&["--record", "runs/a.replay", "--config", "my.toml", "--seed", "9"]);
+        assert_eq!(options.record, PathBuf::from("runs/a.replay"));
+        assert_eq!(options.config, PathBuf::from("my.toml"));
+        assert_eq!(options.seed, Some(9));
+    }
+}
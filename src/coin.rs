@@ -0,0 +1,330 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::collision::{Obb, Rect};
+use crate::config::CoinConfig;
+use crate::pipe::PipeManager;
+
+/// Mixed into the run's seed so coins get a random sequence of their own
+/// and the pipe gaps stay exactly what the seed always gave.
+const COIN_SEED_SALT: u64 = 0x636f_696e_5f73_616c;
+
+/// Where a coin sits in the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoinPlacement {
+    /// Centered in the gap of the pipe pair with this id, following it
+    /// however the gap moves.
+    InGap { pipe_id: u32 },
+    /// Loose between two pairs, scrolling with the world.
+    Free,
+}
+
+/// One slot of the coin pool. `position` is the top-left corner.
+#[derive(Debug, Clone)]
+pub struct Coin {
+    pub position: (f32, f32),
+    pub placement: CoinPlacement,
+    pub active: bool,
+}
+
+impl Coin {
+    pub fn rect(&self, size: f32) -> Rect {
+        Rect::new(self.position.0, self.position.1, size, size)
+    }
+}
+
+/// Places coins along the pipes and picks them up when the bird flies
+/// through one. Like the pipes, coins live in a pool that only ever grows.
+///
+/// Pickups test the bird's hitbox against the coins' sim positions instead
+/// of asking `Canvas::collision_between`, for the same reason as the
+/// shooter: the canvas lags a frame behind the ticks and doesn't exist in a
+/// headless replay, and a replay has to collect the same coins on the same
+/// ticks as the run it was recorded from.
+#[derive(Debug)]
+pub struct CoinManager {
+    pool: Vec<Coin>,
+    /// Coins picked up this run.
+    pub collected: usize,
+    /// Id of the first pipe pair that hasn't been offered a coin yet.
+    next_pipe: u32,
+    rng: StdRng,
+    config: CoinConfig,
+}
+
+impl CoinManager {
+    pub fn new(config: CoinConfig, seed: u64) -> Self {
+        Self {
+            pool: Vec::new(),
+            collected: 0,
+            next_pipe: 0,
+            rng: StdRng::seed_from_u64(seed ^ COIN_SEED_SALT),
+            config,
+        }
+    }
+
+    /// Every slot, active or not, in a stable order.
+    pub fn pool(&self) -> &[Coin] {
+        &self.pool
+    }
+
+    pub fn size(&self) -> f32 {
+        self.config.size
+    }
+
+    /// Offers every pair spawned since the last tick a coin, then moves the
+    /// coins along with the pipes. Call after `PipeManager::update`.
+    pub fn update(&mut self, pipe_manager: &PipeManager) {
+        // At most one coin in and one between every pair on screen.
+        let capacity = pipe_manager.pool().len() * 2;
+        while self.pool.len() < capacity {
+            self.pool.push(Coin {
+                position: (0.0, 0.0),
+                placement: CoinPlacement::Free,
+                active: false,
+            });
+        }
+
+        while self.next_pipe < pipe_manager.pipe_counter {
+            self.offer_coins(pipe_manager, self.next_pipe);
+            self.next_pipe += 1;
+        }
+
+        let half = self.config.size / 2.0;
+        let speed = pipe_manager.speed();
+        for coin in self.pool.iter_mut().filter(|coin| coin.active) {
+            match coin.placement {
+                CoinPlacement::InGap { pipe_id } => {
+                    match pipe_manager.active_pipes().find(|pipe| pipe.id == pipe_id) {
+                        Some(pipe) => coin.position = (pipe.x + pipe_manager.pipe_width / 2.0 - half, pipe.gap_y - half),
+                        None => coin.active = false,
+                    }
+                }
+                CoinPlacement::Free => {
+                    coin.position.0 += speed;
+                    if coin.position.0 < -self.config.size {
+                        coin.active = false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Both rolls are made for every pair, even with coins turned off, so
+    /// the coin sequence for a seed never depends on the settings.
+    fn offer_coins(&mut self, pipe_manager: &PipeManager, pipe_id: u32) {
+        let in_gap = self.rng.random_bool(f64::from(self.config.chance));
+        let between = self.rng.random_bool(f64::from(self.config.between_chance));
+        if !self.config.enabled {
+            return;
+        }
+
+        let Some(pipe) = pipe_manager.active_pipes().find(|pipe| pipe.id == pipe_id) else {
+            return;
+        };
+        let half = self.config.size / 2.0;
+        let center_x = pipe.x + pipe_manager.pipe_width / 2.0;
+
+        if in_gap {
+            self.spawn((center_x - half, pipe.gap_y - half), CoinPlacement::InGap { pipe_id });
+        }
+
+        let previous = pipe_id.checked_sub(1)
+            .and_then(|id| pipe_manager.active_pipes().find(|pipe| pipe.id == id));
+        if let (true, Some(previous)) = (between, previous) {
+            let x = (previous.x + pipe.x) / 2.0 + pipe_manager.pipe_width / 2.0;
+            let y = (previous.gap_y + pipe.gap_y) / 2.0;
+            self.spawn((x - half, y - half), CoinPlacement::Free);
+        }
+    }
+
+    fn spawn(&mut self, position: (f32, f32), placement: CoinPlacement) {
+        if let Some(coin) = self.pool.iter_mut().find(|coin| !coin.active) {
+            *coin = Coin { position, placement, active: true };
+        }
+    }
+
+    /// Picks up every coin touching `hitbox`. Returns true if there was one.
+    pub fn collect(&mut self, hitbox: &Obb) -> bool {
+        let size = self.config.size;
+        let mut picked_up = false;
+        for coin in self.pool.iter_mut().filter(|coin| coin.active) {
+            if hitbox.intersects(&coin.rect(size)) {
+                coin.active = false;
+                self.collected += 1;
+                picked_up = true;
+            }
+        }
+        picked_up
+    }
+
+    /// Takes the coin out of a gap that is needed for something else.
+    pub fn clear_gap(&mut self, pipe_id: u32) {
+        for coin in &mut self.pool {
+            if coin.placement == (CoinPlacement::InGap { pipe_id }) {
+                coin.active = false;
+            }
+        }
+    }
+
+    /// Moves every coin within `radius` of `target` up to `pull` pixels
+    /// towards it. A coin pulled out of a gap stays loose from then on.
+    pub fn attract(&mut self, target: (f32, f32), radius: f32, pull: f32) {
+        let half = self.config.size / 2.0;
+        for coin in self.pool.iter_mut().filter(|coin| coin.active) {
+            let dx = target.0 - (coin.position.0 + half);
+            let dy = target.1 - (coin.position.1 + half);
+            let distance = (dx * dx + dy * dy).sqrt();
+            if distance > radius || distance <= 0.0 {
+                continue;
+            }
+
+            let step = pull.min(distance);
+            coin.position.0 += dx / distance * step;
+            coin.position.1 += dy / distance * step;
+            coin.placement = CoinPlacement::Free;
+        }
+    }
+
+    /// Keeps loose coins inside `gap_range` after a resize, coins in a gap
+    /// follow their pipe on the next update anyway.
+    pub fn resize(&mut self, gap_range: (f32, f32)) {
+        let half = self.config.size / 2.0;
+        for coin in self.pool.iter_mut().filter(|coin| coin.active && coin.placement == CoinPlacement::Free) {
+            coin.position.1 = (coin.position.1 + half).clamp(gap_range.0, gap_range.1) - half;
+        }
+    }
+
+    pub fn reset(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed ^ COIN_SEED_SALT);
+        for coin in &mut self.pool {
+            coin.active = false;
+        }
+        self.collected = 0;
+        self.next_pipe = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::difficulty::Difficulty;
+    use crate::mode::GameMode;
+    use crate::wallet::WalletStore;
+
+    const SEED: u64 = 7;
+
+    fn pipes() -> PipeManager {
+        let config = GameConfig::default();
+        let mut pipe_manager = PipeManager::new(&config, config.layout.canvas_size, SEED, GameMode::Classic);
+        pipe_manager.set_difficulty(Difficulty::base(&config));
+        pipe_manager
+    }
+
+    fn always() -> CoinConfig {
+        CoinConfig { chance: 1.0, between_chance: 1.0, ..CoinConfig::default() }
+    }
+
+    /// Runs the pipes and coins until `pairs` pipe pairs have spawned.
+    fn run(pipe_manager: &mut PipeManager, coins: &mut CoinManager, pairs: u32) {
+        while pipe_manager.pipe_counter < pairs {
+            pipe_manager.update();
+            coins.update(pipe_manager);
+        }
+    }
+
+    fn active(coins: &CoinManager) -> impl Iterator<Item = &Coin> {
+        coins.pool().iter().filter(|coin| coin.active)
+    }
+
+    #[test]
+    fn coins_sit_in_gaps_and_between_pairs() {
+        let mut pipe_manager = pipes();
+        let mut coins = CoinManager::new(always(), SEED);
+        run(&mut pipe_manager, &mut coins, 3);
+
+        let half = coins.size() / 2.0;
+        for pipe in pipe_manager.active_pipes() {
+            let coin = active(&coins)
+                .find(|coin| coin.placement == CoinPlacement::InGap { pipe_id: pipe.id })
+                .expect("every pair has a coin");
+            assert_eq!(coin.position, (pipe.x + pipe_manager.pipe_width / 2.0 - half, pipe.gap_y - half));
+        }
+        // None before the first pair, one between each pair after it.
+        assert_eq!(active(&coins).filter(|coin| coin.placement == CoinPlacement::Free).count(), 2);
+    }
+
+    #[test]
+    fn disabled_coins_never_spawn() {
+        let mut pipe_manager = pipes();
+        let mut coins = CoinManager::new(CoinConfig { enabled: false, ..always() }, SEED);
+        run(&mut pipe_manager, &mut coins, 5);
+        assert_eq!(active(&coins).count(), 0);
+    }
+
+    #[test]
+    fn coins_leave_with_their_pipe() {
+        let mut pipe_manager = pipes();
+        let mut coins = CoinManager::new(CoinConfig { between_chance: 0.0, ..always() }, SEED);
+        run(&mut pipe_manager, &mut coins, 1);
+        assert!(active(&coins).any(|coin| coin.placement == CoinPlacement::InGap { pipe_id: 0 }));
+        while pipe_manager.active_pipes().any(|pipe| pipe.id == 0) {
+            pipe_manager.update();
+            coins.update(&pipe_manager);
+        }
+        assert!(!active(&coins).any(|coin| coin.placement == CoinPlacement::InGap { pipe_id: 0 }));
+    }
+
+    #[test]
+    fn flying_through_a_coin_picks_it_up() {
+        let mut pipe_manager = pipes();
+        let mut coins = CoinManager::new(CoinConfig { between_chance: 0.0, ..always() }, SEED);
+        run(&mut pipe_manager, &mut coins, 1);
+
+        let coin = active(&coins).next().unwrap().clone();
+        let size = coins.size();
+        let miss = Obb::from(Rect::new(coin.position.0 + size * 2.0, coin.position.1, size, size));
+        assert!(!coins.collect(&miss));
+
+        let hit = Obb::new(Rect::new(coin.position.0 + size / 2.0, coin.position.1, size, size), 0.5);
+        assert!(coins.collect(&hit));
+        assert!(!coins.collect(&hit), "a coin is only picked up once");
+        assert_eq!(coins.collected, 1);
+        assert_eq!(active(&coins).count(), 0);
+    }
+
+    #[test]
+    fn collected_coins_add_up_in_the_wallet() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut wallet = WalletStore::open(Some(dir.path().join("wallet.toml")));
+        let mut pipe_manager = pipes();
+        let mut coins = CoinManager::new(CoinConfig { between_chance: 0.0, ..always() }, SEED);
+
+        for _ in 0..2 {
+            run(&mut pipe_manager, &mut coins, 2);
+            let everywhere = Obb::from(Rect::new(-1000.0, -1000.0, 5000.0, 5000.0));
+            assert!(coins.collect(&everywhere));
+            assert_eq!(coins.collected, 2);
+            wallet.deposit(coins.collected);
+
+            pipe_manager.reset(SEED);
+            coins.reset(SEED);
+            assert_eq!(coins.collected, 0);
+        }
+        assert_eq!(wallet.wallet.coins, 4);
+    }
+
+    #[test]
+    fn same_seed_same_coins() {
+        let positions = |config: CoinConfig| {
+            let mut pipe_manager = pipes();
+            let mut coins = CoinManager::new(config, SEED);
+            run(&mut pipe_manager, &mut coins, 10);
+            active(&coins).map(|coin| coin.position).collect::<Vec<_>>()
+        };
+        let config = CoinConfig::default();
+        assert!(!positions(config).is_empty());
+        assert_eq!(positions(config), positions(config));
+    }
+}
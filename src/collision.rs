@@ -0,0 +1,150 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.w
+            && self.x + self.w > other.x
+            && self.y < other.y + other.h
+            && self.y + self.h > other.y
+    }
+
+    /// Shrinks (or grows) the rect around its own center.
+    pub fn scaled(&self, scale: f32) -> Rect {
+        let w = self.w * scale;
+        let h = self.h * scale;
+        Rect::new(self.x + (self.w - w) / 2.0, self.y + (self.h - h) / 2.0, w, h)
+    }
+}
+
+/// A rectangle turned by `angle` radians around its center. Positive turns
+/// clockwise on screen, since y points down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Obb {
+    pub center: (f32, f32),
+    /// Half the width and height before turning.
+    pub half: (f32, f32),
+    pub angle: f32,
+}
+
+impl Obb {
+    pub fn new(rect: Rect, angle: f32) -> Self {
+        Self {
+            center: (rect.x + rect.w / 2.0, rect.y + rect.h / 2.0),
+            half: (rect.w / 2.0, rect.h / 2.0),
+            angle,
+        }
+    }
+
+    /// Shrinks (or grows) the box around its own center.
+    pub fn scaled(&self, scale: f32) -> Obb {
+        Obb { half: (self.half.0 * scale, self.half.1 * scale), ..*self }
+    }
+
+    /// The smallest upright rect the box fits in.
+    pub fn bounds(&self) -> Rect {
+        let (sin, cos) = self.angle.sin_cos();
+        let extent_x = cos.abs() * self.half.0 + sin.abs() * self.half.1;
+        let extent_y = sin.abs() * self.half.0 + cos.abs() * self.half.1;
+        Rect::new(self.center.0 - extent_x, self.center.1 - extent_y, extent_x * 2.0, extent_y * 2.0)
+    }
+
+    /// Separating axis test against an upright rect. The rect's own axes
+    /// are covered by the bounds check, which leaves the box's two.
+    pub fn intersects(&self, rect: &Rect) -> bool {
+        if !self.bounds().intersects(rect) {
+            return false;
+        }
+
+        let (sin, cos) = self.angle.sin_cos();
+        let corners = [
+            (rect.x, rect.y),
+            (rect.x + rect.w, rect.y),
+            (rect.x, rect.y + rect.h),
+            (rect.x + rect.w, rect.y + rect.h),
+        ];
+
+        [((cos, sin), self.half.0), ((-sin, cos), self.half.1)].iter().all(|(axis, half)| {
+            let project = |(x, y): (f32, f32)| (x - self.center.0) * axis.0 + (y - self.center.1) * axis.1;
+            let (min, max) = corners.iter().fold((f32::MAX, f32::MIN), |(min, max), corner| {
+                let distance = project(*corner);
+                (min.min(distance), max.max(distance))
+            });
+            min < *half && max > -half
+        })
+    }
+}
+
+impl From<Rect> for Obb {
+    fn from(rect: Rect) -> Self {
+        Obb::new(rect, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_4;
+
+    #[test]
+    fn touching_rects_dont_intersect() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        assert!(rect.intersects(&Rect::new(9.0, 9.0, 10.0, 10.0)));
+        assert!(!rect.intersects(&Rect::new(10.0, 0.0, 10.0, 10.0)));
+        assert!(!rect.intersects(&Rect::new(0.0, 10.0, 10.0, 10.0)));
+    }
+
+    #[test]
+    fn scaling_keeps_the_center() {
+        assert_eq!(Rect::new(0.0, 0.0, 20.0, 10.0).scaled(0.5), Rect::new(5.0, 2.5, 10.0, 5.0));
+        let obb = Obb::new(Rect::new(0.0, 0.0, 20.0, 10.0), 1.0).scaled(0.5);
+        assert_eq!(obb.center, (10.0, 5.0));
+        assert_eq!(obb.half, (5.0, 2.5));
+    }
+
+    #[test]
+    fn bounds_grow_with_the_turn() {
+        let upright = Obb::new(Rect::new(0.0, 0.0, 60.0, 20.0), 0.0);
+        assert_eq!(upright.bounds(), Rect::new(0.0, 0.0, 60.0, 20.0));
+
+        let bounds = Obb::new(Rect::new(0.0, 0.0, 60.0, 20.0), std::f32::consts::FRAC_PI_2).bounds();
+        assert!((bounds.w - 20.0).abs() < 1e-4 && (bounds.h - 60.0).abs() < 1e-4, "{:?}", bounds);
+        assert!((bounds.x - 20.0).abs() < 1e-4 && (bounds.y + 20.0).abs() < 1e-4, "{:?}", bounds);
+    }
+
+    #[test]
+    fn a_turned_box_reaches_a_corner_it_misses_upright() {
+        // A long flat box centered at (100, 100), and the bottom-left corner
+        // of a pipe just above its right end.
+        let rect = Rect::new(70.0, 90.0, 60.0, 20.0);
+        let pipe = Rect::new(120.0, 0.0, 50.0, 85.0);
+
+        assert!(!Obb::new(rect, 0.0).intersects(&pipe));
+        // Nose up, the right end swings up into the pipe.
+        assert!(Obb::new(rect, -FRAC_PI_4).intersects(&pipe));
+        // Nose down, it swings away.
+        assert!(!Obb::new(rect, FRAC_PI_4).intersects(&pipe));
+    }
+
+    #[test]
+    fn overlapping_bounds_alone_are_not_a_hit() {
+        // Turned 45 degrees the square is a diamond reaching 14.1 out from
+        // its center along each axis, so its bounds cover the rect's corner
+        // at (8, 8) while the diamond itself stops short of it.
+        let diamond = Obb::new(Rect::new(-10.0, -10.0, 20.0, 20.0), FRAC_PI_4);
+        let rect = Rect::new(8.0, 8.0, 12.0, 12.0);
+        assert!(diamond.bounds().intersects(&rect));
+        assert!(!diamond.intersects(&rect));
+
+        assert!(diamond.intersects(&Rect::new(6.0, 6.0, 12.0, 12.0)));
+    }
+}
@@ -0,0 +1,991 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::{Action, Binding, MenuKey};
+use crate::savefile;
+
+/// Every tuning value in the game. Loaded from a TOML file at startup; any
+/// section or field the file leaves out keeps its built-in default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameConfig {
+    pub physics: PhysicsConfig,
+    pub tilt: TiltConfig,
+    pub spawning: SpawnConfig,
+    pub motion: MotionConfig,
+    pub coins: CoinConfig,
+    pub power_ups: PowerUpConfig,
+    pub shooter: ShooterConfig,
+    pub layout: LayoutConfig,
+    pub hud: HudConfig,
+    pub medals: MedalConfig,
+    pub death: DeathConfig,
+    pub scenery: SceneryConfig,
+    pub audio: AudioConfig,
+    /// Changes made on the controls screen are written back here.
+    pub input: InputConfig,
+    /// Sprite sheet to draw the bird, pipes, ground and digits from, e.g.
+    /// `sprites.png`. It is sliced by the descriptor next to it, and
+    /// anything the descriptor doesn't name keeps its own PNG.
+    pub sprite_sheet: Option<String>,
+    /// Points on the difficulty curve, in increasing score order. Values
+    /// are interpolated between them and held after the last one. Before
+    /// the first, the curve starts from `physics` and `spawning` at 0. An
+    /// empty list keeps the pipes the same for the whole run.
+    pub difficulty: Vec<DifficultyStep>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            physics: PhysicsConfig::default(),
+            tilt: TiltConfig::default(),
+            spawning: SpawnConfig::default(),
+            motion: MotionConfig::default(),
+            coins: CoinConfig::default(),
+            power_ups: PowerUpConfig::default(),
+            shooter: ShooterConfig::default(),
+            layout: LayoutConfig::default(),
+            hud: HudConfig::default(),
+            medals: MedalConfig::default(),
+            death: DeathConfig::default(),
+            scenery: SceneryConfig::default(),
+            audio: AudioConfig::default(),
+            input: InputConfig::default(),
+            sprite_sheet: None,
+            difficulty: vec![
+                DifficultyStep { score: 20, world_speed: -4.0, interval: 1.6, gap_size: 180.0 },
+                DifficultyStep { score: 50, world_speed: -5.0, interval: 1.3, gap_size: 150.0 },
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PhysicsConfig {
+    /// Added to the bird's vertical speed every tick.
+    pub gravity: f32,
+    /// Vertical speed a flap sets the bird to, however fast it was going
+    /// before. Negative is up.
+    pub flap_impulse: f32,
+    /// Fastest the bird falls, in pixels per tick.
+    pub max_fall_speed: f32,
+    /// Horizontal speed of the pipes and the ground, in pixels per tick.
+    pub world_speed: f32,
+    /// Fraction of the bird sprite that counts for collisions.
+    pub hitbox_scale: f32,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            gravity: 0.30,
+            flap_impulse: -10.5,
+            max_fall_speed: 10.0,
+            world_speed: -3.0,
+            hitbox_scale: 0.85,
+        }
+    }
+}
+
+/// Most poses the bird is pre-rotated to. Each one is a copy of every
+/// frame of the bird.
+const MAX_BIRD_POSES: f32 = 64.0;
+
+/// How the bird turns with its vertical speed. Angles are in degrees,
+/// negative noses up.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TiltConfig {
+    /// Angle right after a flap.
+    pub max_up: f32,
+    /// Angle when falling at `physics.max_fall_speed`.
+    pub max_down: f32,
+    /// Fraction of the way to its target angle the bird turns every tick.
+    pub smoothing: f32,
+    /// Angle between two of the poses the sprite is pre-rotated to.
+    pub pose_step: f32,
+    /// Seconds the wings beat faster after a flap.
+    pub fast_wings: f32,
+    /// How many times faster they beat meanwhile.
+    pub fast_wing_rate: f32,
+}
+
+impl Default for TiltConfig {
+    fn default() -> Self {
+        Self {
+            max_up: -25.0,
+            max_down: 90.0,
+            smoothing: 0.2,
+            pose_step: 7.5,
+            fast_wings: 0.3,
+            fast_wing_rate: 2.0,
+        }
+    }
+}
+
+impl TiltConfig {
+    /// How many poses it takes to cover `max_up` to `max_down` in steps of
+    /// `pose_step`, the last one maybe a shorter step.
+    pub fn pose_count(&self) -> f32 {
+        ((self.max_down - self.max_up) / self.pose_step).ceil() + 1.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpawnConfig {
+    /// Seconds between two pipe pairs.
+    pub interval: f32,
+    pub gap_size: f32,
+    /// Highest the center of a gap may sit, measured from the top.
+    pub min_gap_y: f32,
+    /// Space kept between the bottom of a gap and the ground.
+    pub ground_margin: f32,
+    /// How far right of the canvas new pipes appear.
+    pub spawn_offset: f32,
+    /// How far past the left edge pipes travel before they are removed.
+    pub despawn_margin: f32,
+}
+
+impl Default for SpawnConfig {
+    fn default() -> Self {
+        Self {
+            interval: 2.0,
+            gap_size: 220.0,
+            min_gap_y: 150.0,
+            ground_margin: 10.0,
+            spawn_offset: 100.0,
+            despawn_margin: 50.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MotionConfig {
+    /// Score from which new pairs may move. Hard mode moves every pair
+    /// from the start.
+    pub from_score: usize,
+    /// Relative odds of a new pair staying still, bobbing, sliding its gap
+    /// or breathing.
+    pub weights: [f32; 4],
+    /// How far a bobbing gap drifts above and below where it spawned.
+    pub bob_amplitude: f32,
+    /// Radians per tick.
+    pub bob_speed: f32,
+    /// Horizontal distance a sliding gap takes to reach its new height.
+    pub slide_length: f32,
+    /// Narrowest a breathing gap gets, as a fraction of its full size.
+    pub breathe_min_scale: f32,
+    /// Radians per tick.
+    pub breathe_speed: f32,
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        Self {
+            from_score: 15,
+            weights: [6.0, 2.0, 1.0, 1.0],
+            bob_amplitude: 40.0,
+            bob_speed: 0.055,
+            slide_length: 160.0,
+            breathe_min_scale: 0.7,
+            breathe_speed: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CoinConfig {
+    pub enabled: bool,
+    /// Odds of a new pipe pair carrying a coin in its gap, from 0 to 1.
+    pub chance: f32,
+    /// Odds of a coin halfway between a new pair and the one before it.
+    pub between_chance: f32,
+    pub size: f32,
+}
+
+impl Default for CoinConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            chance: 0.5,
+            between_chance: 0.25,
+            size: 24.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerUpConfig {
+    pub enabled: bool,
+    /// Odds of a new pipe pair carrying a power-up in its gap, from 0 to 1.
+    /// It takes the place of the gap's coin.
+    pub chance: f32,
+    /// Seconds a power-up runs for.
+    pub duration: f32,
+    /// Most durations one kind can have banked by picking it up again.
+    pub max_stack: u32,
+    pub size: f32,
+    /// Seconds the bird can't be hurt for after its shield breaks.
+    pub shield_grace: f32,
+    /// World speed while slow-motion runs, as a fraction of the normal one.
+    pub slow_motion_scale: f32,
+    /// Applied to `physics.hitbox_scale` while shrink runs.
+    pub shrink_scale: f32,
+    /// Distance from the bird within which coins are pulled in.
+    pub magnet_radius: f32,
+    /// Pixels per tick a pulled coin moves towards the bird.
+    pub magnet_pull: f32,
+}
+
+impl Default for PowerUpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            chance: 0.1,
+            duration: 6.0,
+            max_stack: 2,
+            size: 30.0,
+            shield_grace: 1.0,
+            slow_motion_scale: 0.5,
+            shrink_scale: 0.6,
+            magnet_radius: 180.0,
+            magnet_pull: 8.0,
+        }
+    }
+}
+
+/// Bullets and aliens in shooter mode.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShooterConfig {
+    pub bullet_size: (f32, f32),
+    /// Pixels per tick, to the right.
+    pub bullet_speed: f32,
+    /// Seconds between two shots.
+    pub fire_cooldown: f32,
+    pub alien_size: (f32, f32),
+    /// Odds of an alien between a new pipe pair and the one before it.
+    pub alien_chance: f32,
+    /// Added to the world speed, negative aliens fly towards the bird.
+    pub alien_speed: f32,
+    pub alien_bob_amplitude: f32,
+    /// Radians per tick.
+    pub alien_bob_speed: f32,
+    /// Score for shooting an alien down.
+    pub alien_points: usize,
+}
+
+impl Default for ShooterConfig {
+    fn default() -> Self {
+        Self {
+            bullet_size: (20.0, 10.0),
+            bullet_speed: 9.0,
+            fire_cooldown: 0.25,
+            alien_size: (40.0, 40.0),
+            alien_chance: 0.6,
+            alien_speed: -0.5,
+            alien_bob_amplitude: 30.0,
+            alien_bob_speed: 0.08,
+            alien_points: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// Size of the canvas until the window reports its real size.
+    pub canvas_size: (f32, f32),
+    /// Where the bird starts on a `canvas_size` canvas. Other sizes scale it.
+    pub bird_start: (f32, f32),
+    pub bird_size: (f32, f32),
+    pub pipe_width: f32,
+    pub pipe_height: f32,
+    pub base_width: f32,
+    pub base_height: f32,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            canvas_size: (800.0, 600.0),
+            bird_start: (200.0, 300.0),
+            bird_size: (50.0, 35.0),
+            pipe_width: 100.0 * 0.5,
+            pipe_height: 800.0,
+            base_width: 336.0,
+            base_height: 112.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HudConfig {
+    pub digit_size: (f32, f32),
+    pub digit_spacing: f32,
+    /// Distance of the score from the top-right corner.
+    pub margin: f32,
+}
+
+impl Default for HudConfig {
+    fn default() -> Self {
+        Self {
+            digit_size: (24.0, 38.0),
+            digit_spacing: 5.0,
+            margin: 20.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MedalConfig {
+    /// Scores for bronze, silver, gold and platinum, in increasing order.
+    pub thresholds: [usize; 4],
+}
+
+impl Default for MedalConfig {
+    fn default() -> Self {
+        Self {
+            thresholds: [10, 20, 30, 40],
+        }
+    }
+}
+
+/// The inputs bound to every action. One input may trigger several
+/// actions, the settings screen keeps each on a single one though.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    pub flap: Vec<Binding>,
+    pub pause: Vec<Binding>,
+    pub restart: Vec<Binding>,
+    pub fire: Vec<Binding>,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        let keys = |names: &[&str]| names.iter().map(|name| Binding::Key(name.to_string())).collect::<Vec<_>>();
+        Self {
+            flap: [keys(&["w", "Space", "ArrowUp"]), vec![Binding::Click]].concat(),
+            pause: keys(&["p", "Escape"]),
+            restart: keys(&["r"]),
+            fire: keys(&["f"]),
+        }
+    }
+}
+
+impl InputConfig {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        match action {
+            Action::Flap => &self.flap,
+            Action::Pause => &self.pause,
+            Action::Restart => &self.restart,
+            Action::Fire => &self.fire,
+        }
+    }
+
+    fn bindings_mut(&mut self, action: Action) -> &mut Vec<Binding> {
+        match action {
+            Action::Flap => &mut self.flap,
+            Action::Pause => &mut self.pause,
+            Action::Restart => &mut self.restart,
+            Action::Fire => &mut self.fire,
+        }
+    }
+
+    /// Every action `binding` triggers.
+    pub fn actions(&self, binding: &Binding) -> impl Iterator<Item = Action> + '_ {
+        let binding = binding.clone();
+        Action::ALL.into_iter().filter(move |action| self.bindings(*action).contains(&binding))
+    }
+
+    /// Adds `binding` to `action`, taking it off any other action first.
+    /// Menu keys are refused, and false is returned.
+    pub fn bind(&mut self, action: Action, binding: Binding) -> bool {
+        if MenuKey::for_binding(&binding).is_some() {
+            return false;
+        }
+        for other in Action::ALL {
+            self.bindings_mut(other).retain(|bound| *bound != binding);
+        }
+        self.bindings_mut(action).push(binding);
+        true
+    }
+
+    /// Removes the binding added last to `action`. Flap always keeps one,
+    /// the game can't be played without it.
+    pub fn unbind_last(&mut self, action: Action) -> Option<Binding> {
+        let bindings = self.bindings_mut(action);
+        if action == Action::Flap && bindings.len() <= 1 {
+            return None;
+        }
+        bindings.pop()
+    }
+
+    /// Everything that keeps these bindings from being used.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.flap.is_empty() {
+            problems.push("input.flap needs at least one binding".to_string());
+        }
+        for action in Action::ALL {
+            for binding in self.bindings(action).iter().filter(|binding| MenuKey::for_binding(binding).is_some()) {
+                problems.push(format!("input.{} can't use {}, it is kept for the menus", action.name(), binding));
+            }
+        }
+        problems
+    }
+}
+
+/// The crash, from the hit to the game-over panel. Times are in seconds
+/// from the hit, and 0 turns an effect off.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeathConfig {
+    /// How long the white flash takes to fade out.
+    pub flash: f32,
+    /// How long the world shakes.
+    pub shake: f32,
+    /// How far the world is thrown at the start of the shake, in pixels.
+    pub shake_amplitude: f32,
+    /// Shortest time the bird falls for. The panel waits for it to land
+    /// and for the flash and shake to end as well.
+    pub fall: f32,
+}
+
+impl Default for DeathConfig {
+    fn default() -> Self {
+        Self {
+            flash: 0.25,
+            shake: 0.35,
+            shake_amplitude: 8.0,
+            fall: 0.75,
+        }
+    }
+}
+
+impl DeathConfig {
+    /// Seconds before the panel may show.
+    pub fn length(&self) -> f32 {
+        self.fall.max(self.flash).max(self.shake)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneryConfig {
+    pub background: LayerConfig,
+    /// Extra layers drawn between the background and the ground.
+    pub clouds: Vec<LayerConfig>,
+}
+
+impl Default for SceneryConfig {
+    fn default() -> Self {
+        Self {
+            background: LayerConfig {
+                asset: "bg.png".to_string(),
+                speed: 0.2,
+                depth: 100,
+                y: 0.0,
+                height: None,
+            },
+            clouds: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayerConfig {
+    pub asset: String,
+    /// Scroll speed relative to the pipes, 1.0 keeps pace with them.
+    pub speed: f32,
+    /// Layers with a higher depth are drawn further back.
+    pub depth: i32,
+    pub y: f32,
+    /// Tile height, the full canvas height when left out. The tile width
+    /// follows the image's aspect ratio.
+    pub height: Option<f32>,
+}
+
+impl Default for LayerConfig {
+    fn default() -> Self {
+        Self {
+            asset: String::new(),
+            speed: 0.5,
+            depth: 50,
+            y: 0.0,
+            height: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    /// Scales everything the game plays, from 0 to 1.
+    pub master_volume: f32,
+    /// Scales sound effects on top of `master_volume`, from 0 to 1.
+    pub sfx_volume: f32,
+    /// Scales the music on top of `master_volume`, from 0 to 1.
+    pub music_volume: f32,
+    /// Scores at which the second and third music stems fade in.
+    pub intensity_scores: [usize; 2],
+    pub muted: bool,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            sfx_volume: 0.8,
+            music_volume: 0.6,
+            intensity_scores: [10, 25],
+            muted: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DifficultyStep {
+    pub score: usize,
+    /// Same meaning as `physics.world_speed`.
+    pub world_speed: f32,
+    /// Same meaning as `spawning.interval`.
+    pub interval: f32,
+    pub gap_size: f32,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Invalid(Vec<String>),
+    /// The file to save the controls into isn't valid TOML.
+    Edit(toml_edit::TomlError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "could not read config: {}", err),
+            ConfigError::Parse(err) => write!(f, "could not parse config: {}", err),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid config:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+            ConfigError::Edit(err) => write!(f, "could not update config: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(err: toml::de::Error) -> Self {
+        ConfigError::Parse(err)
+    }
+}
+
+impl From<toml_edit::TomlError> for ConfigError {
+    fn from(err: toml_edit::TomlError) -> Self {
+        ConfigError::Edit(err)
+    }
+}
+
+impl GameConfig {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let config: GameConfig = toml::from_str(&fs::read_to_string(path)?)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Loads `path` when it exists and falls back to the built-in defaults
+    /// otherwise. A file that exists but can't be used is reported first.
+    pub fn load_or_default(path: &Path) -> Self {
+        match Self::load(path) {
+            Ok(config) => config,
+            Err(ConfigError::Io(err)) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                eprintln!("Ignoring {} and using the default config, {}", path.display(), err);
+                Self::default()
+            }
+        }
+    }
+
+    /// Writes `input` into the `[input]` table of the config at `path`,
+    /// creating the file if there is none. Nothing else in the file
+    /// changes, comments and formatting included.
+    pub fn save_input(path: &Path, input: &InputConfig) -> Result<(), ConfigError> {
+        let mut document = match fs::read_to_string(path) {
+            Ok(text) => text.parse::<toml_edit::DocumentMut>()?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => toml_edit::DocumentMut::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        let Some(table) = document.entry("input").or_insert(toml_edit::table()).as_table_like_mut() else {
+            return Err(ConfigError::Invalid(vec!["input must be a table".to_string()]));
+        };
+        for action in Action::ALL {
+            let names = input.bindings(action).iter().map(|binding| binding.to_string()).collect::<toml_edit::Array>();
+            table.insert(action.name(), toml_edit::value(names));
+        }
+
+        savefile::write_text(path, &document.to_string())?;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        let mut positive = |name: &str, value: f32| {
+            if value.is_nan() || value <= 0.0 {
+                problems.push(format!("{} must be greater than 0 (got {})", name, value));
+            }
+        };
+
+        positive("physics.gravity", self.physics.gravity);
+        positive("physics.max_fall_speed", self.physics.max_fall_speed);
+        positive("tilt.smoothing", self.tilt.smoothing);
+        positive("tilt.pose_step", self.tilt.pose_step);
+        positive("tilt.fast_wing_rate", self.tilt.fast_wing_rate);
+        positive("physics.hitbox_scale", self.physics.hitbox_scale);
+        positive("spawning.interval", self.spawning.interval);
+        positive("spawning.gap_size", self.spawning.gap_size);
+        positive("motion.slide_length", self.motion.slide_length);
+        positive("motion.breathe_min_scale", self.motion.breathe_min_scale);
+        positive("coins.size", self.coins.size);
+        positive("power_ups.duration", self.power_ups.duration);
+        positive("power_ups.size", self.power_ups.size);
+        positive("power_ups.slow_motion_scale", self.power_ups.slow_motion_scale);
+        positive("power_ups.shrink_scale", self.power_ups.shrink_scale);
+        positive("power_ups.magnet_radius", self.power_ups.magnet_radius);
+        positive("power_ups.magnet_pull", self.power_ups.magnet_pull);
+        positive("shooter.bullet_size width", self.shooter.bullet_size.0);
+        positive("shooter.bullet_size height", self.shooter.bullet_size.1);
+        positive("shooter.bullet_speed", self.shooter.bullet_speed);
+        positive("shooter.fire_cooldown", self.shooter.fire_cooldown);
+        positive("shooter.alien_size width", self.shooter.alien_size.0);
+        positive("shooter.alien_size height", self.shooter.alien_size.1);
+        positive("layout.canvas_size width", self.layout.canvas_size.0);
+        positive("layout.canvas_size height", self.layout.canvas_size.1);
+        positive("layout.bird_size width", self.layout.bird_size.0);
+        positive("layout.bird_size height", self.layout.bird_size.1);
+        positive("layout.pipe_width", self.layout.pipe_width);
+        positive("layout.pipe_height", self.layout.pipe_height);
+        positive("layout.base_width", self.layout.base_width);
+        positive("layout.base_height", self.layout.base_height);
+        positive("hud.digit_size width", self.hud.digit_size.0);
+        positive("hud.digit_size height", self.hud.digit_size.1);
+
+        if self.physics.flap_impulse.is_nan() || self.physics.flap_impulse >= 0.0 {
+            problems.push(format!("physics.flap_impulse must be negative so a flap goes up (got {})", self.physics.flap_impulse));
+        }
+        if self.physics.world_speed.is_nan() || self.physics.world_speed >= 0.0 {
+            problems.push(format!("physics.world_speed must be negative so the world scrolls left (got {})", self.physics.world_speed));
+        }
+        if self.motion.weights.iter().any(|weight| weight.is_nan() || *weight < 0.0) {
+            problems.push(format!("motion.weights must not be negative (got {:?})", self.motion.weights));
+        }
+        if self.motion.breathe_min_scale > 1.0 {
+            problems.push(format!("motion.breathe_min_scale must be at most 1 (got {})", self.motion.breathe_min_scale));
+        }
+        if self.tilt.max_up.is_nan() || self.tilt.max_down.is_nan() || self.tilt.max_up >= self.tilt.max_down {
+            problems.push(format!("tilt.max_up ({}) must be below tilt.max_down ({})", self.tilt.max_up, self.tilt.max_down));
+        }
+        let poses = self.tilt.pose_count();
+        if poses.is_nan() || poses > MAX_BIRD_POSES {
+            problems.push(format!("tilt.pose_step ({}) is too small, the bird would need more than {} poses", self.tilt.pose_step, MAX_BIRD_POSES));
+        }
+        if self.tilt.fast_wings.is_nan() || self.tilt.fast_wings < 0.0 {
+            problems.push(format!("tilt.fast_wings must not be negative (got {})", self.tilt.fast_wings));
+        }
+        for (name, value) in [
+            ("death.flash", self.death.flash),
+            ("death.shake", self.death.shake),
+            ("death.shake_amplitude", self.death.shake_amplitude),
+            ("death.fall", self.death.fall),
+        ] {
+            if value.is_nan() || value < 0.0 {
+                problems.push(format!("{} must not be negative (got {})", name, value));
+            }
+        }
+        if self.power_ups.max_stack == 0 {
+            problems.push("power_ups.max_stack must be at least 1".to_string());
+        }
+        if self.power_ups.shield_grace.is_nan() || self.power_ups.shield_grace < 0.0 {
+            problems.push(format!("power_ups.shield_grace must not be negative (got {})", self.power_ups.shield_grace));
+        }
+        if self.physics.hitbox_scale > 1.0 {
+            problems.push(format!("physics.hitbox_scale must be at most 1 (got {})", self.physics.hitbox_scale));
+        }
+
+        let mut fraction = |name: &str, value: f32| {
+            if !(0.0..=1.0).contains(&value) {
+                problems.push(format!("{} must be between 0 and 1 (got {})", name, value));
+            }
+        };
+
+        fraction("audio.master_volume", self.audio.master_volume);
+        fraction("audio.sfx_volume", self.audio.sfx_volume);
+        fraction("audio.music_volume", self.audio.music_volume);
+        fraction("coins.chance", self.coins.chance);
+        fraction("coins.between_chance", self.coins.between_chance);
+        fraction("power_ups.chance", self.power_ups.chance);
+        fraction("power_ups.slow_motion_scale", self.power_ups.slow_motion_scale);
+        fraction("power_ups.shrink_scale", self.power_ups.shrink_scale);
+        fraction("shooter.alien_chance", self.shooter.alien_chance);
+        fraction("tilt.smoothing", self.tilt.smoothing);
+
+        problems.extend(self.input.problems());
+        if self.medals.thresholds.windows(2).any(|pair| pair[0] >= pair[1]) {
+            problems.push(format!("medals.thresholds must be in increasing order (got {:?})", self.medals.thresholds));
+        }
+        if self.audio.intensity_scores[0] > self.audio.intensity_scores[1] {
+            problems.push(format!("audio.intensity_scores must be in increasing order (got {:?})", self.audio.intensity_scores));
+        }
+
+        let max_gap_y = max_gap_y(&self.spawning, &self.layout, self.layout.canvas_size.1);
+        if self.spawning.min_gap_y >= max_gap_y {
+            problems.push(format!(
+                "spawning.min_gap_y ({}) leaves no room for a gap of {} above the ground, it must be below {}",
+                self.spawning.min_gap_y, self.spawning.gap_size, max_gap_y
+            ));
+        }
+
+        let mut previous_score = 0;
+        for (idx, step) in self.difficulty.iter().enumerate() {
+            let name = format!("difficulty[{}]", idx);
+            if idx > 0 && step.score <= previous_score {
+                problems.push(format!("{}.score must be higher than the step before it (got {})", name, step.score));
+            }
+            if step.world_speed.is_nan() || step.world_speed >= 0.0 {
+                problems.push(format!("{}.world_speed must be negative (got {})", name, step.world_speed));
+            }
+            if step.interval.is_nan() || step.interval <= 0.0 {
+                problems.push(format!("{}.interval must be greater than 0 (got {})", name, step.interval));
+            }
+            if step.gap_size.is_nan() || step.gap_size <= 0.0 {
+                problems.push(format!("{}.gap_size must be greater than 0 (got {})", name, step.gap_size));
+            }
+            previous_score = step.score;
+        }
+
+        let layers = std::iter::once(("scenery.background".to_string(), &self.scenery.background))
+            .chain(self.scenery.clouds.iter().enumerate().map(|(idx, layer)| (format!("scenery.clouds[{}]", idx), layer)));
+        for (name, layer) in layers {
+            if layer.asset.is_empty() {
+                problems.push(format!("{}.asset must name an image", name));
+            }
+            if layer.speed.is_nan() || layer.speed < 0.0 {
+                problems.push(format!("{}.speed must not be negative (got {})", name, layer.speed));
+            }
+            if let Some(height) = layer.height.filter(|height| height.is_nan() || *height <= 0.0) {
+                problems.push(format!("{}.height must be greater than 0 (got {})", name, height));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
+
+/// Lowest a gap center may sit on a canvas of `canvas_height`.
+pub fn max_gap_y(spawning: &SpawnConfig, layout: &LayoutConfig, canvas_height: f32) -> f32 {
+    canvas_height - layout.base_height - spawning.gap_size / 2.0 - spawning.ground_margin
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> Binding {
+        Binding::Key(name.to_string())
+    }
+
+    /// What `validate` finds wrong with a config file holding `text`.
+    fn problems(text: &str) -> Vec<String> {
+        let config: GameConfig = toml::from_str(text).unwrap();
+        match config.validate() {
+            Ok(()) => Vec::new(),
+            Err(ConfigError::Invalid(problems)) => problems,
+            Err(err) => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(GameConfig::default().validate().is_ok());
+        assert!(problems("").is_empty());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let err = toml::from_str::<GameConfig>("[physics]\ngravty = 0.5\n").unwrap_err();
+        assert!(err.to_string().contains("unknown field `gravty`"), "{}", err);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rampy.toml");
+        fs::write(&path, "[physic]\ngravity = 0.5\n").unwrap();
+        assert!(matches!(GameConfig::load(&path), Err(ConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn difficulty_steps_must_increase() {
+        let text = "
+            [[difficulty]]
+            score = 20
+            world_speed = -4.0
+            interval = 1.6
+            gap_size = 180.0
+
+            [[difficulty]]
+            score = 20
+            world_speed = 4.0
+            interval = 1.3
+            gap_size = 150.0
+        ";
+        assert_eq!(problems(text), [
+            "difficulty[1].score must be higher than the step before it (got 20)",
+            "difficulty[1].world_speed must be negative (got 4)",
+        ]);
+    }
+
+    #[test]
+    fn gaps_must_fit_above_the_ground() {
+        // 600 tall, less 112 of ground, half the 220 gap and the 10 margin.
+        assert!(problems("[spawning]\nmin_gap_y = 367.0\n").is_empty());
+        assert_eq!(problems("[spawning]\nmin_gap_y = 400.0\n"), [
+            "spawning.min_gap_y (400) leaves no room for a gap of 220 above the ground, it must be below 368",
+        ]);
+    }
+
+    #[test]
+    fn pose_step_must_keep_the_pose_count_down() {
+        assert_eq!(problems("[tilt]\npose_step = 1.0\n"), [
+            "tilt.pose_step (1) is too small, the bird would need more than 64 poses",
+        ]);
+        assert_eq!(problems("[tilt]\npose_step = nan\n"), [
+            "tilt.pose_step must be greater than 0 (got NaN)",
+            "tilt.pose_step (NaN) is too small, the bird would need more than 64 poses",
+        ]);
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let text = "
+            [physics]
+            gravity = 0.0
+            flap_impulse = 5.0
+
+            [medals]
+            thresholds = [40, 30, 20, 10]
+        ";
+        assert_eq!(problems(text), [
+            "physics.gravity must be greater than 0 (got 0)",
+            "physics.flap_impulse must be negative so a flap goes up (got 5)",
+            "medals.thresholds must be in increasing order (got [40, 30, 20, 10])",
+        ]);
+    }
+
+    #[test]
+    fn binding_moves_an_input_off_other_actions() {
+        let mut input = InputConfig::default();
+        assert!(input.bind(Action::Fire, key("w")));
+        assert!(!input.flap.contains(&key("w")));
+        assert_eq!(input.fire, [key("f"), key("w")]);
+        assert_eq!(input.actions(&key("w")).collect::<Vec<_>>(), [Action::Fire]);
+    }
+
+    #[test]
+    fn menu_keys_are_refused() {
+        let mut input = InputConfig::default();
+        assert!(!input.bind(Action::Flap, key("Enter")));
+        assert!(!input.bind(Action::Fire, key("m")));
+        assert_eq!(input.flap, InputConfig::default().flap);
+        assert_eq!(input.fire, [key("f")]);
+    }
+
+    #[test]
+    fn flap_keeps_its_last_binding() {
+        let mut input = InputConfig::default();
+        while input.unbind_last(Action::Flap).is_some() {}
+        assert_eq!(input.flap, [key("w")]);
+
+        assert_eq!(input.unbind_last(Action::Restart), Some(key("r")));
+        assert_eq!(input.unbind_last(Action::Restart), None);
+    }
+
+    #[test]
+    fn input_problems() {
+        assert!(InputConfig::default().problems().is_empty());
+
+        let input = InputConfig { flap: Vec::new(), pause: vec![key("Tab")], ..InputConfig::default() };
+        assert_eq!(input.problems(), [
+            "input.flap needs at least one binding",
+            "input.pause can't use Tab, it is kept for the menus",
+        ]);
+    }
+
+    #[test]
+    fn saving_controls_only_touches_the_input_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rampy.toml");
+        fs::write(&path, "# Tuned for the demo\n[physics]\ngravity = 0.25 # floaty\n\n[input]\nflap = [\"Space\"]\n").unwrap();
+
+        let mut input = InputConfig { flap: vec![key("Space")], ..InputConfig::default() };
+        input.bind(Action::Flap, Binding::Click);
+        GameConfig::save_input(&path, &input).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# Tuned for the demo\n[physics]\ngravity = 0.25 # floaty\n"));
+        let config = GameConfig::load(&path).unwrap();
+        assert_eq!(config.physics.gravity, 0.25);
+        assert_eq!(config.input.flap, [key("Space"), Binding::Click]);
+        assert_eq!(config.input.fire, [key("f")]);
+    }
+
+    #[test]
+    fn saving_controls_creates_the_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rampy.toml");
+
+        GameConfig::save_input(&path, &InputConfig::default()).unwrap();
+        assert_eq!(GameConfig::load(&path).unwrap().input.flap, InputConfig::default().flap);
+    }
+
+    #[test]
+    fn saving_controls_leaves_a_broken_config_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rampy.toml");
+        fs::write(&path, "[physics\n").unwrap();
+
+        assert!(matches!(GameConfig::save_input(&path, &InputConfig::default()), Err(ConfigError::Edit(_))));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[physics\n");
+    }
+}
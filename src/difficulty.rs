@@ -0,0 +1,136 @@
+use crate::config::{DifficultyStep, GameConfig};
+
+/// The pipe settings in effect at one point of a run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Difficulty {
+    pub world_speed: f32,
+    pub interval: f32,
+    pub gap_size: f32,
+}
+
+impl Difficulty {
+    /// Where every run starts, straight from `physics` and `spawning`.
+    pub fn base(config: &GameConfig) -> Self {
+        Self {
+            world_speed: config.physics.world_speed,
+            interval: config.spawning.interval,
+            gap_size: config.spawning.gap_size,
+        }
+    }
+
+    /// Follows `config.difficulty` to `score`, interpolating linearly
+    /// between the two steps around it.
+    pub fn at(config: &GameConfig, score: usize) -> Self {
+        let mut from = (0, Self::base(config));
+
+        for step in &config.difficulty {
+            let to = Self::from(*step);
+            if score < step.score {
+                let t = (score - from.0) as f32 / (step.score - from.0) as f32;
+                return from.1.lerp(&to, t);
+            }
+            from = (step.score, to);
+        }
+
+        from.1
+    }
+
+    /// The base settings and every step of the curve. Anything the curve
+    /// produces lies between these.
+    pub fn corners(config: &GameConfig) -> impl Iterator<Item = Self> + '_ {
+        std::iter::once(Self::base(config)).chain(config.difficulty.iter().map(|step| Self::from(*step)))
+    }
+
+    /// Horizontal distance between two pipe pairs, in pixels.
+    pub fn spacing(&self, tick_rate: f32) -> f32 {
+        self.world_speed.abs() * self.interval * tick_rate
+    }
+
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Self {
+            world_speed: mix(self.world_speed, other.world_speed),
+            interval: mix(self.interval, other.interval),
+            gap_size: mix(self.gap_size, other.gap_size),
+        }
+    }
+}
+
+impl From<DifficultyStep> for Difficulty {
+    fn from(step: DifficultyStep) -> Self {
+        Self {
+            world_speed: step.world_speed,
+            interval: step.interval,
+            gap_size: step.gap_size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> GameConfig {
+        let mut config = GameConfig::default();
+        config.physics.world_speed = -3.0;
+        config.spawning.interval = 2.0;
+        config.spawning.gap_size = 220.0;
+        config.difficulty = vec![
+            DifficultyStep { score: 20, world_speed: -4.0, interval: 1.6, gap_size: 180.0 },
+            DifficultyStep { score: 50, world_speed: -5.0, interval: 1.0, gap_size: 150.0 },
+        ];
+        config
+    }
+
+    fn assert_close(actual: Difficulty, expected: Difficulty) {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-4;
+        assert!(
+            close(actual.world_speed, expected.world_speed)
+                && close(actual.interval, expected.interval)
+                && close(actual.gap_size, expected.gap_size),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn interpolates_between_steps() {
+        let config = config();
+        assert_eq!(Difficulty::at(&config, 0), Difficulty::base(&config));
+        assert_close(Difficulty::at(&config, 10), Difficulty { world_speed: -3.5, interval: 1.8, gap_size: 200.0 });
+        assert_close(Difficulty::at(&config, 20), Difficulty::from(config.difficulty[0]));
+        assert_close(Difficulty::at(&config, 35), Difficulty { world_speed: -4.5, interval: 1.3, gap_size: 165.0 });
+    }
+
+    #[test]
+    fn holds_the_last_step() {
+        let config = config();
+        let last = Difficulty::from(config.difficulty[1]);
+        assert_eq!(Difficulty::at(&config, 50), last);
+        assert_eq!(Difficulty::at(&config, 1000), last);
+    }
+
+    #[test]
+    fn no_steps_keeps_the_base() {
+        let config = GameConfig { difficulty: Vec::new(), ..config() };
+        assert_eq!(Difficulty::at(&config, 500), Difficulty::base(&config));
+        assert_eq!(Difficulty::corners(&config).count(), 1);
+    }
+
+    #[test]
+    fn corners_bound_the_curve() {
+        let config = config();
+        let corners = Difficulty::corners(&config).collect::<Vec<_>>();
+        assert_eq!(corners, [
+            Difficulty::base(&config),
+            Difficulty::from(config.difficulty[0]),
+            Difficulty::from(config.difficulty[1]),
+        ]);
+
+        let tightest = corners.iter().map(|corner| corner.spacing(60.0)).fold(f32::INFINITY, f32::min);
+        for score in 0..80 {
+            assert!(Difficulty::at(&config, score).spacing(60.0) >= tightest - 1e-3, "score {}", score);
+        }
+    }
+}
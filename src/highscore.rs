@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+// std::time::SystemTime::now panics on wasm32.
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::mode::GameMode;
+use crate::paths;
+use crate::savefile::{self, SaveError};
+
+/// How many runs each mode's leaderboard keeps.
+pub const TABLE_SIZE: usize = 10;
+const SAVE_FILE: &str = "highscores.toml";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub score: usize,
+    /// Day the run ended, as `YYYY-MM-DD` in UTC.
+    pub date: String,
+    pub seed: u64,
+    pub mode: String,
+}
+
+/// One mode's all-time best and top runs, best first. `best` is kept on its
+/// own so it survives the table being edited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreTable {
+    pub best: usize,
+    pub entries: Vec<ScoreEntry>,
+}
+
+/// Everything in the save file: a table per mode, keyed by mode name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighScores {
+    pub tables: BTreeMap<String, ScoreTable>,
+    // Saves from before modes existed hold a single table at the top level.
+    // It is moved into `tables` on load and never written back.
+    #[serde(skip_serializing)]
+    best: usize,
+    #[serde(skip_serializing)]
+    entries: Vec<ScoreEntry>,
+}
+
+impl ScoreTable {
+    /// Adds a finished run. Returns its place on the table, starting at 0,
+    /// or None when it didn't make the cut.
+    pub fn record(&mut self, entry: ScoreEntry) -> Option<usize> {
+        self.best = self.best.max(entry.score);
+
+        // Ties go below the runs that got there first.
+        let rank = self.entries.iter().position(|existing| existing.score < entry.score).unwrap_or(self.entries.len());
+        if rank >= TABLE_SIZE {
+            return None;
+        }
+
+        self.entries.insert(rank, entry);
+        self.entries.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+
+    /// Puts a table edited by hand back in order.
+    fn tidy(&mut self) {
+        self.entries.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        self.entries.truncate(TABLE_SIZE);
+        self.best = self.entries.iter().map(|entry| entry.score).fold(self.best, usize::max);
+    }
+}
+
+impl HighScores {
+    pub fn table(&self, mode: GameMode) -> Option<&ScoreTable> {
+        self.tables.get(mode.name())
+    }
+
+    pub fn best(&self, mode: GameMode) -> usize {
+        self.table(mode).map_or(0, |table| table.best)
+    }
+
+    pub fn record(&mut self, entry: ScoreEntry) -> Option<usize> {
+        self.tables.entry(entry.mode.clone()).or_default().record(entry)
+    }
+
+    /// Moves a pre-modes table into `tables` and tidies every table.
+    fn normalize(mut self) -> Self {
+        let legacy_best = std::mem::take(&mut self.best);
+        for entry in std::mem::take(&mut self.entries) {
+            self.tables.entry(entry.mode.clone()).or_default().entries.push(entry);
+        }
+        if legacy_best > 0 {
+            let classic = self.tables.entry(GameMode::Classic.name().to_string()).or_default();
+            classic.best = classic.best.max(legacy_best);
+        }
+
+        for table in self.tables.values_mut() {
+            table.tidy();
+        }
+        self
+    }
+}
+
+/// The high scores together with where they are saved. Without a data
+/// directory the scores only last until the game closes.
+#[derive(Debug)]
+pub struct HighScoreStore {
+    pub scores: HighScores,
+    path: Option<PathBuf>,
+}
+
+impl HighScoreStore {
+    pub fn open_default() -> Self {
+        Self::open(paths::data_dir().map(|dir| dir.join(SAVE_FILE)))
+    }
+
+    /// Reads the save file, see `savefile::load` for what happens to a
+    /// damaged one.
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let scores = match &path {
+            Some(path) => savefile::load::<HighScores>(path).normalize(),
+            None => HighScores::default(),
+        };
+        Self { scores, path }
+    }
+
+    pub fn best(&self, mode: GameMode) -> usize {
+        self.scores.best(mode)
+    }
+
+    /// Records a run on its mode's table and saves straight away. A failed
+    /// save is reported but the run still counts for this session.
+    pub fn record(&mut self, score: usize, seed: u64, mode: GameMode) -> Option<usize> {
+        let rank = self.scores.record(ScoreEntry {
+            score,
+            date: today(),
+            seed,
+            mode: mode.name().to_string(),
+        });
+
+        if let Err(err) = self.save() {
+            eprintln!("{}", err);
+        }
+        rank
+    }
+
+    pub fn save(&self) -> Result<(), SaveError> {
+        match &self.path {
+            Some(path) => savefile::write(path, &self.scores),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Today's date in UTC as `YYYY-MM-DD`.
+fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs() / 86_400).unwrap_or(0);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date, using
+/// Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil_from_days_known_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(civil_from_days(20_513), (2026, 3, 1));
+    }
+
+    #[test]
+    fn today_is_a_date() {
+        let date = today();
+        assert_eq!(date.len(), 10);
+        assert!(date.as_str() > "2020-01-01");
+    }
+
+    #[test]
+    fn records_survive_a_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SAVE_FILE);
+
+        let mut store = HighScoreStore::open(Some(path.clone()));
+        assert_eq!(store.record(5, 1, GameMode::Classic), Some(0));
+        assert_eq!(store.record(9, 2, GameMode::Classic), Some(0));
+        assert_eq!(store.record(3, 3, GameMode::Zen), Some(0));
+
+        let store = HighScoreStore::open(Some(path));
+        assert_eq!(store.best(GameMode::Classic), 9);
+        assert_eq!(store.best(GameMode::Zen), 3);
+        let scores = store.scores.table(GameMode::Classic).unwrap().entries.iter().map(|entry| entry.score).collect::<Vec<_>>();
+        assert_eq!(scores, vec![9, 5]);
+    }
+
+    #[test]
+    fn only_the_top_runs_are_kept() {
+        let mut table = ScoreTable::default();
+        for score in 0..TABLE_SIZE {
+            table.record(ScoreEntry { score: score + 1, date: String::new(), seed: 0, mode: "classic".to_string() });
+        }
+        let low = ScoreEntry { score: 0, date: String::new(), seed: 0, mode: "classic".to_string() };
+        assert_eq!(table.record(low), None);
+        assert_eq!(table.entries.len(), TABLE_SIZE);
+        assert_eq!(table.best, TABLE_SIZE);
+    }
+}
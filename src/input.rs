@@ -0,0 +1,262 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use prism::drawable::SizedTree;
+use prism::event::{OnEvent, Event, Key, NamedKey, KeyboardEvent, KeyboardState, MouseEvent, MouseState};
+use prism::Context;
+use serde::{Deserialize, Serialize};
+use stork::Canvas;
+
+/// What the player can do, whatever input it comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Flap,
+    Pause,
+    /// Ends the run from the pause screen, or starts the next one from the
+    /// game-over panel.
+    Restart,
+    Fire,
+}
+
+impl Action {
+    pub const ALL: [Action; 4] = [Action::Flap, Action::Pause, Action::Restart, Action::Fire];
+
+    pub fn index(&self) -> usize {
+        Self::ALL.iter().position(|action| action == self).unwrap_or(0)
+    }
+
+    /// The action `steps` places along, wrapping at both ends.
+    pub fn cycle(&self, steps: i32) -> Self {
+        let len = Self::ALL.len() as i32;
+        Self::ALL[(self.index() as i32 + steps).rem_euclid(len) as usize]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Flap => "flap",
+            Action::Pause => "pause",
+            Action::Restart => "restart",
+            Action::Fire => "fire",
+        }
+    }
+}
+
+/// Named keys that can be bound, by the name used in the config file.
+const NAMED_KEYS: [&str; 9] = ["Space", "ArrowUp", "ArrowDown", "ArrowLeft", "ArrowRight", "Enter", "Escape", "Backspace", "Tab"];
+
+/// One physical input. Prism reports a tap on a touch screen as a mouse
+/// press, so `Click` covers taps too, and `Tap` is accepted as its name.
+///
+/// In the config file a binding is a string: a single character like `"w"`,
+/// a named key like `"Space"` or `"ArrowUp"`, or `"Click"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    /// A key by its config name, characters in lowercase.
+    Key(String),
+    Click,
+}
+
+impl Binding {
+    /// The binding for a key press, None for keys that can't be bound.
+    pub fn from_key(key: &Key) -> Option<Self> {
+        let name = match key {
+            Key::Character(c) => c.as_str().to_lowercase(),
+            Key::Named(named) => named_key_name(named)?.to_string(),
+        };
+        Some(Binding::Key(name))
+    }
+
+    /// Short name for the settings screen.
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Key(name) => match name.as_str() {
+                "ArrowUp" => "UP".to_string(),
+                "ArrowDown" => "DOWN".to_string(),
+                "ArrowLeft" => "LEFT".to_string(),
+                "ArrowRight" => "RIGHT".to_string(),
+                "Escape" => "ESC".to_string(),
+                "Backspace" => "BKSP".to_string(),
+                name => name.to_uppercase(),
+            },
+            Binding::Click => "CLICK".to_string(),
+        }
+    }
+}
+
+fn named_key_name(key: &NamedKey) -> Option<&'static str> {
+    match key {
+        NamedKey::Space => Some("Space"),
+        NamedKey::ArrowUp => Some("ArrowUp"),
+        NamedKey::ArrowDown => Some("ArrowDown"),
+        NamedKey::ArrowLeft => Some("ArrowLeft"),
+        NamedKey::ArrowRight => Some("ArrowRight"),
+        NamedKey::Enter => Some("Enter"),
+        NamedKey::Escape => Some("Escape"),
+        NamedKey::Backspace => Some("Backspace"),
+        NamedKey::Tab => Some("Tab"),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(name) => write!(f, "{}", name),
+            Binding::Click => write!(f, "Click"),
+        }
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        if name == "Click" || name == "Tap" {
+            return Ok(Binding::Click);
+        }
+        if name.chars().count() == 1 {
+            return Ok(Binding::Key(name.to_lowercase()));
+        }
+        if NAMED_KEYS.contains(&name.as_str()) {
+            return Ok(Binding::Key(name));
+        }
+        Err(format!("unknown input '{}', expected a single character, Click or one of {}", name, NAMED_KEYS.join(", ")))
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.to_string()
+    }
+}
+
+/// Keys kept for working the menus. None of them can be bound to an action,
+/// so a key never moves through a menu and flaps at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuKey {
+    Mute,
+    Left,
+    Right,
+    Confirm,
+    Unbind,
+    Settings,
+}
+
+impl MenuKey {
+    /// The menu key `binding` is, if it is one.
+    pub fn for_binding(binding: &Binding) -> Option<Self> {
+        let Binding::Key(name) = binding else {
+            return None;
+        };
+        match name.as_str() {
+            "m" => Some(MenuKey::Mute),
+            "a" | "ArrowLeft" => Some(MenuKey::Left),
+            "d" | "ArrowRight" => Some(MenuKey::Right),
+            "Enter" => Some(MenuKey::Confirm),
+            "Backspace" => Some(MenuKey::Unbind),
+            "Tab" => Some(MenuKey::Settings),
+            _ => None,
+        }
+    }
+}
+
+/// A key or mouse press waiting for the game to pick it up.
+#[derive(Debug, Clone)]
+pub enum Press {
+    Key(Key),
+    Click((f32, f32)),
+}
+
+/// The stork canvas, noting the presses in the events that pass through it.
+/// The game takes them on its next update, which comes with the next event.
+#[derive(Debug)]
+pub struct InputCanvas {
+    canvas: Canvas,
+    presses: Vec<Press>,
+}
+
+impl InputCanvas {
+    pub fn new(canvas: Canvas) -> Self {
+        Self { canvas, presses: Vec::new() }
+    }
+
+    pub fn take_presses(&mut self) -> Vec<Press> {
+        std::mem::take(&mut self.presses)
+    }
+}
+
+impl OnEvent for InputCanvas {
+    fn on_event(&mut self, ctx: &mut Context, tree: &SizedTree, event: Box<dyn Event>) -> Vec<Box<dyn Event>> {
+        if let Some(KeyboardEvent { key, state: KeyboardState::Pressed }) = event.downcast_ref::<KeyboardEvent>() {
+            self.presses.push(Press::Key(key.clone()));
+        }
+
+        if let Some(MouseEvent { position: Some(position), state: MouseState::Pressed }) = event.downcast_ref::<MouseEvent>() {
+            self.presses.push(Press::Click(*position));
+        }
+
+        self.canvas.on_event(ctx, tree, event)
+    }
+}
+
+impl Deref for InputCanvas {
+    type Target = Canvas;
+
+    fn deref(&self) -> &Canvas {
+        &self.canvas
+    }
+}
+
+impl DerefMut for InputCanvas {
+    fn deref_mut(&mut self) -> &mut Canvas {
+        &mut self.canvas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(name: &str) -> Result<Binding, String> {
+        Binding::try_from(name.to_string())
+    }
+
+    #[test]
+    fn config_names() {
+        assert_eq!(parse("W"), Ok(Binding::Key("w".to_string())));
+        assert_eq!(parse("ArrowUp"), Ok(Binding::Key("ArrowUp".to_string())));
+        assert_eq!(parse("Click"), Ok(Binding::Click));
+        assert_eq!(parse("Tap"), Ok(Binding::Click));
+        assert!(parse("PageDown").is_err());
+        assert_eq!(String::from(parse("Tap").unwrap()), "Click");
+    }
+
+    #[test]
+    fn menu_keys_are_reserved() {
+        let reserved = [
+            ("m", MenuKey::Mute),
+            ("a", MenuKey::Left),
+            ("ArrowLeft", MenuKey::Left),
+            ("d", MenuKey::Right),
+            ("ArrowRight", MenuKey::Right),
+            ("Enter", MenuKey::Confirm),
+            ("Backspace", MenuKey::Unbind),
+            ("Tab", MenuKey::Settings),
+        ];
+        for (name, menu_key) in reserved {
+            assert_eq!(MenuKey::for_binding(&parse(name).unwrap()), Some(menu_key), "{}", name);
+        }
+
+        for name in ["w", "Space", "ArrowUp", "Escape", "Click"] {
+            assert_eq!(MenuKey::for_binding(&parse(name).unwrap()), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(parse("w").unwrap().label(), "W");
+        assert_eq!(parse("ArrowDown").unwrap().label(), "DOWN");
+        assert_eq!(parse("Tap").unwrap().label(), "CLICK");
+    }
+}
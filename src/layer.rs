@@ -0,0 +1,163 @@
+use prism::Context;
+use prism::canvas::{Image, ShapeType};
+use stork::{Canvas, GameObject};
+
+/// A strip of identical tiles that scrolls horizontally and wraps around.
+/// `speed` is relative to the world, so 1.0 keeps pace with the pipes and
+/// smaller values give a parallax effect. Layers with a higher `depth` sit
+/// further back and are added to the canvas first.
+#[derive(Debug)]
+pub struct ScrollingLayer {
+    pub name: String,
+    pub tile_size: (f32, f32),
+    pub y: f32,
+    pub speed: f32,
+    pub depth: i32,
+    image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    tags: Vec<String>,
+    tile_count: usize,
+}
+
+impl ScrollingLayer {
+    pub fn new(
+        name: &str,
+        image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+        tile_size: (f32, f32),
+        y: f32,
+        speed: f32,
+        depth: i32,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            tile_size,
+            y,
+            speed,
+            depth,
+            image,
+            tags: vec![name.to_string()],
+            tile_count: 0,
+        }
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Enough tiles to cover `canvas_width` at any scroll offset.
+    pub fn tiles_needed(&self, canvas_width: f32) -> usize {
+        (canvas_width / self.tile_size.0).ceil() as usize + 1
+    }
+
+    fn tile_name(&self, idx: usize) -> String {
+        format!("{}_{}", self.name, idx)
+    }
+
+    pub fn tile_names(&self) -> impl Iterator<Item = String> + '_ {
+        (0..self.tile_count).map(|idx| self.tile_name(idx))
+    }
+
+    pub fn spawn(&mut self, ctx: &mut Context, canvas: &mut Canvas, canvas_width: f32) {
+        self.despawn(canvas);
+        self.tile_count = self.tiles_needed(canvas_width);
+
+        for idx in 0..self.tile_count {
+            let img_obj = Image {
+                shape: ShapeType::Rectangle(0.0, self.tile_size, 0.0),
+                image: self.image.clone().into(),
+                color: None
+            };
+
+            let tile = GameObject::new_rect(
+                ctx,
+                self.tile_name(idx),
+                img_obj,
+                self.tile_size,
+                (self.tile_size.0 * idx as f32, self.y),
+                self.tags.clone(),
+                (0.0, 0.0),
+                (1.0, 1.0),
+                0.0,
+            );
+
+            canvas.add_game_object(self.tile_name(idx), tile);
+        }
+    }
+
+    pub fn despawn(&mut self, canvas: &mut Canvas) {
+        for idx in 0..self.tile_count {
+            canvas.remove_game_object(&self.tile_name(idx));
+        }
+        self.tile_count = 0;
+    }
+
+    /// Lines the tiles up for a world that has scrolled `distance` pixels.
+    pub fn sync(&self, canvas: &mut Canvas, distance: f32) {
+        for idx in 0..self.tile_count {
+            if let Some(tile) = canvas.get_game_object_mut(&self.tile_name(idx)) {
+                tile.position = (self.tile_x(idx, distance), self.y);
+            }
+        }
+    }
+
+    /// Left edge of tile `idx` once the world has scrolled `distance`
+    /// pixels. The first tile never starts right of 0 or a full tile left of
+    /// it, and each one starts where the one before it ends.
+    fn tile_x(&self, idx: usize, distance: f32) -> f32 {
+        let offset = (distance * self.speed).rem_euclid(self.tile_size.0);
+        self.tile_size.0 * idx as f32 - offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(tile_width: f32, speed: f32) -> ScrollingLayer {
+        ScrollingLayer::new("clouds", image::RgbaImage::new(1, 1), (tile_width, 100.0), 0.0, speed, 1)
+    }
+
+    #[test]
+    fn tiles_needed_covers_any_offset() {
+        let layer = layer(336.0, 1.0);
+        assert_eq!(layer.tiles_needed(800.0), 4);
+        assert_eq!(layer.tiles_needed(672.0), 3);
+        assert_eq!(layer.tiles_needed(1.0), 2);
+
+        // Scrolled by almost a whole tile, the last one still reaches the edge.
+        for width in [1.0, 335.0, 336.0, 337.0, 800.0, 1920.0] {
+            let last = layer.tiles_needed(width) - 1;
+            assert!(layer.tile_x(last, 335.9) + layer.tile_size.0 >= width, "{}", width);
+        }
+    }
+
+    #[test]
+    fn tiles_wrap_without_seams() {
+        let layer = layer(336.0, 0.5);
+        let count = layer.tiles_needed(800.0);
+
+        let mut distance = 0.0;
+        while distance < 5000.0 {
+            let first = layer.tile_x(0, distance);
+            assert!(first <= 0.0 && first > -layer.tile_size.0, "tile 0 at {} for distance {}", first, distance);
+            for idx in 1..count {
+                let step = layer.tile_x(idx, distance) - layer.tile_x(idx - 1, distance);
+                assert!((step - layer.tile_size.0).abs() < 1e-3, "a seam of {} at distance {}", step - layer.tile_size.0, distance);
+            }
+            assert!(layer.tile_x(count - 1, distance) + layer.tile_size.0 >= 800.0);
+            distance += 3.7;
+        }
+    }
+
+    #[test]
+    fn scrolling_a_tile_width_lands_back_in_place() {
+        let layer = layer(336.0, 0.5);
+        let wrap = layer.tile_size.0 / layer.speed;
+        for distance in [0.0, 100.0, 671.0] {
+            let x = layer.tile_x(0, distance);
+            assert!((layer.tile_x(0, distance + wrap) - x).abs() < 1e-3);
+        }
+        // Negative distances wrap the same way, never leaving a gap at 0.
+        assert!((layer.tile_x(0, -10.0) - -(336.0 - 5.0)).abs() < 1e-3);
+    }
+}
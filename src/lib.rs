@@ -1,19 +1,63 @@
 use prism::drawable::{Drawable, Component, SizedTree};
-use prism::event::{OnEvent, Event, Key};
-use prism::{Context, canvas::{Image, ShapeType}};
+use prism::event::{OnEvent, Event};
+use prism::Context;
 use prism::layout::{SizeRequest, Area};
 
-use stork::{Canvas, GameObject, Action, Target, GameEvent, AnimatedSprite};
+use std::cell::Cell;
+use std::path::PathBuf;
 
+use stork::{Canvas, Action, Target, GameEvent};
+
+mod assets;
+mod atlas;
+mod audio;
+mod bird;
+mod cli;
+mod coin;
+mod collision;
+mod config;
+mod difficulty;
+mod font;
+mod highscore;
+mod input;
+mod layer;
+mod medal;
+mod mode;
 mod pipe;
+mod paths;
+mod powerup;
+mod render;
+mod replay;
+mod savefile;
 mod score;
+mod screen;
+mod shooter;
+mod state;
+mod timestep;
+mod wallet;
+
+use assets::Assets;
+use audio::{Audio, MusicCue, Sound};
+use cli::LaunchOptions;
+use config::InputConfig;
+use highscore::HighScoreStore;
+use input::{Binding, InputCanvas, MenuKey, Press};
+use medal::Medal;
+use render::{Button, Overlay, Renderer};
+use replay::ReplayMode;
+use screen::{Screen, GAME_OVER_INPUT_DELAY};
+use timestep::{FixedTimestep, TICK_RATE};
+use wallet::WalletStore;
 
-use pipe::PipeManager;
-use score::ScoreManager;
+pub use replay::{Replay, ReplayError, ReplayPlayer};
+pub use config::{ConfigError, GameConfig};
+pub use mode::GameMode;
+pub use state::{GameState, StepEvents};
 
 //AVAILABLE IMAGES
     //bg.png
     //bullet.png
+    //alien.png
     //player.pngk
     //flappybird.png
     //toppipe.png
@@ -35,12 +79,49 @@ use score::ScoreManager;
 
 #[derive(Debug)]
 pub struct Game {
-    canvas: Canvas,
-    canvas_size: (f32, f32),
-    pipe_manager: PipeManager,
-    score_manager: ScoreManager,
-    base_width: f32,
-    game_over: bool,
+    canvas: InputCanvas,
+    /// Latest size handed to `build`, picked up on the next update.
+    canvas_size: Cell<(f32, f32)>,
+    state: GameState,
+    renderer: Renderer,
+    audio: Audio,
+    high_scores: HighScoreStore,
+    wallet: WalletStore,
+    timestep: FixedTimestep,
+    screen: Screen,
+    screen_ticks: u64,
+    input: InputConfig,
+    /// The config file the game was launched with, where rebound controls
+    /// are saved.
+    config_path: PathBuf,
+    flap_queued: bool,
+    /// Shots only count while playing, a press on any other screen is dropped.
+    fire_queued: bool,
+    pause_queued: bool,
+    /// Ends the run from the pause screen, the only way out of Zen mode.
+    restart_queued: bool,
+    /// Mode menu moves queued by the arrow keys, negative is left.
+    menu_step: i32,
+    /// Presses the highlighted game-over button, or starts rebinding.
+    confirm_queued: bool,
+    /// Takes the last input off the selected action in the settings.
+    unbind_queued: bool,
+    settings_queued: bool,
+    /// Where the last click landed, for the overlay buttons.
+    click_queued: Option<(f32, f32)>,
+    /// The game-over button Enter presses.
+    selected_button: Button,
+    /// The action the settings screen rebinds.
+    settings_action: input::Action,
+    /// The settings screen waits for an input to bind, which doesn't
+    /// trigger anything else meanwhile.
+    listening: bool,
+    captured: Option<Binding>,
+    /// The menu key the settings screen last refused to bind.
+    rejected: Option<Binding>,
+    fixed_seed: Option<u64>,
+    replay: ReplayMode,
+    record_path: PathBuf,
 }
 
 ///! No adding code into this impl, keep it how it is :)
@@ -54,11 +135,11 @@ impl OnEvent for Game {
 
 impl Component for Game {
     fn children(&self) -> Vec<&dyn Drawable> {
-        vec![&self.canvas]
+        vec![&*self.canvas]
     }
     
     fn children_mut(&mut self) -> Vec<&mut dyn Drawable> {
-        vec![&mut self.canvas]
+        vec![&mut *self.canvas]
     }
     
     fn request_size(&self, children: Vec<SizeRequest>) -> SizeRequest {
@@ -67,6 +148,7 @@ impl Component for Game {
     
     fn build(&self, size: (f32, f32), _children: Vec<SizeRequest>) -> Vec<Area> {
         self.canvas.update_size(size);
+        self.canvas_size.set(size);
         
         vec![Area {
             offset: (0.0, 0.0),
@@ -76,277 +158,512 @@ impl Component for Game {
 }
 
 impl Game {
+    /// Turns keys, clicks and taps into the actions they are bound to.
+    fn handle_input(&mut self, press: Press) {
+        let binding = match press {
+            Press::Key(key) => Binding::from_key(&key),
+            Press::Click(position) => {
+                self.click_queued = Some(position);
+                Some(Binding::Click)
+            }
+        };
+        
+        let Some(binding) = binding else {
+            return;
+        };
+        if self.listening {
+            self.captured = Some(binding);
+            return;
+        }
+        if let Some(menu_key) = MenuKey::for_binding(&binding) {
+            self.handle_menu_key(menu_key);
+            return;
+        }
+        
+        for action in self.input.actions(&binding) {
+            match action {
+                input::Action::Flap => self.flap_queued = true,
+                input::Action::Pause => self.pause_queued = true,
+                input::Action::Restart => self.restart_queued = true,
+                input::Action::Fire => self.fire_queued = true,
+            }
+        }
+    }
+    
+    /// Keys that work the menus. They can't be bound, see `MenuKey`.
+    fn handle_menu_key(&mut self, key: MenuKey) {
+        match key {
+            MenuKey::Mute => self.audio.set_muted(!self.audio.is_muted()),
+            MenuKey::Left => self.menu_step -= 1,
+            MenuKey::Right => self.menu_step += 1,
+            MenuKey::Confirm => self.confirm_queued = true,
+            MenuKey::Unbind => self.unbind_queued = true,
+            MenuKey::Settings => self.settings_queued = true,
+        }
+    }
+
     fn update_game(&mut self, ctx: &mut Context) {
-        self.check_collisions(ctx);
+        for press in self.canvas.take_presses() {
+            self.handle_input(press);
+        }
+        self.follow_canvas_size(ctx);
         
-        if self.game_over {
+        let ticks = self.timestep.advance();
+        if ticks == 0 {
             return;
         }
         
-        self.pipe_manager.update(ctx, &mut self.canvas);
-        self.update_base_positions();
-        self.score_manager.check_score(&self.canvas, self.pipe_manager.pipe_counter, self.pipe_manager.pipe_width);
-        self.score_manager.update_display(ctx, &mut self.canvas);
-        self.check_ceiling_collision();
+        for _ in 0..ticks {
+            self.tick(ctx);
+        }
+        
+        self.renderer.sync(ctx, &mut self.canvas, &self.state);
     }
     
-    fn check_collisions(&mut self, ctx: &mut Context) {
-        let bird_target = Target::ByName("flappybird".to_string());
-        let obstacle_target = Target::ByTag("obstacle".to_string());
+    /// Live runs re-lay out as soon as the canvas changes size and note the
+    /// change in the replay. Playback ignores the window and follows the
+    /// resizes recorded in the replay instead, so the run plays out the same.
+    fn follow_canvas_size(&mut self, ctx: &mut Context) {
+        let size = self.canvas_size.get();
+        if size == self.state.canvas_size || size.0 <= 0.0 || size.1 <= 0.0 {
+            return;
+        }
         
-        if self.canvas.collision_between(&bird_target, &obstacle_target) {
-            self.game_over = true;
-            self.handle_game_over(ctx);
+        match &mut self.replay {
+            ReplayMode::Recording(replay) => replay.record_resize(self.state.tick, size),
+            ReplayMode::Playback(_) => return,
         }
+        self.relayout(ctx, size);
+    }
+    
+    fn relayout(&mut self, ctx: &mut Context, size: (f32, f32)) {
+        self.state.resize(size);
+        self.renderer.build_scene(ctx, &mut self.canvas, &self.state);
+    }
+    
+    fn tick(&mut self, ctx: &mut Context) {
+        self.step_screen(ctx);
+        self.renderer.advance_overlay(&mut self.canvas);
+        self.audio.update_music(self.music_cue());
     }
     
-    fn check_ceiling_collision(&mut self) {
-        if let Some(bird) = self.canvas.get_game_object_mut("flappybird") {
-            if bird.position.1 <= 0.0 {
-                bird.position.1 = 0.0;
-                bird.momentum.1 = 0.0;
+    fn step_screen(&mut self, ctx: &mut Context) {
+        self.screen_ticks += 1;
+        let pause = std::mem::take(&mut self.pause_queued);
+        let menu_step = std::mem::take(&mut self.menu_step);
+        let restart = std::mem::take(&mut self.restart_queued);
+        let fire = std::mem::take(&mut self.fire_queued);
+        let confirm = std::mem::take(&mut self.confirm_queued);
+        let unbind = std::mem::take(&mut self.unbind_queued);
+        let settings = std::mem::take(&mut self.settings_queued);
+        let click = self.click_queued.take();
+        let clicked = click.and_then(|point| self.renderer.button_at(point));
+        
+        match self.screen {
+            Screen::Title => {
+                // The click on the settings button is a flap too, it mustn't start a run.
+                if settings || clicked == Some(Button::Settings) {
+                    self.flap_queued = false;
+                    self.set_screen(ctx, Screen::Settings);
+                    return;
+                }
+                
+                if menu_step != 0 {
+                    self.select_mode(ctx, self.state.mode.cycle(menu_step));
+                }
+                
+                self.state.hover(self.screen_ticks);
+                if std::mem::take(&mut self.flap_queued) {
+                    self.set_screen(ctx, Screen::GetReady);
+                }
+            }
+            Screen::GetReady => {
+                // The flap is left queued so the first playing tick applies it.
+                let playback = matches!(self.replay, ReplayMode::Playback(_));
+                if self.state.ready(self.screen_ticks, self.flap_queued || playback) {
+                    self.set_screen(ctx, Screen::Playing);
+                }
+            }
+            Screen::Playing => {
+                if pause {
+                    self.set_screen(ctx, Screen::Paused);
+                    return;
+                }
+                
+                if let ReplayMode::Playback(player) = &mut self.replay {
+                    // A run quit from the pause screen ends here, nothing in the sim stops it.
+                    if player.ended_at(self.state.tick) {
+                        self.state.end_run();
+                        self.set_screen(ctx, Screen::GameOver);
+                        return;
+                    }
+                    if let Some(size) = player.resize_at(self.state.tick) {
+                        self.relayout(ctx, size);
+                    }
+                }
+                
+                let flap = self.next_flap();
+                let fire = self.next_fire(fire);
+                let events = self.state.step(flap, fire);
+                
+                if events.flapped {
+                    self.audio.play(Sound::Flap);
+                }
+                
+                if events.fired {
+                    self.audio.play(Sound::Shoot);
+                }
+                
+                if events.aliens_destroyed > 0 {
+                    self.audio.play(Sound::Explode);
+                }
+                
+                if events.scored {
+                    self.audio.play(Sound::Point);
+                }
+                
+                if events.coin_collected {
+                    self.audio.play(Sound::Coin);
+                }
+                
+                if events.power_up.is_some() {
+                    self.audio.play(Sound::PowerUp);
+                }
+                
+                if events.shield_broken {
+                    self.audio.play(Sound::Hit);
+                }
+                
+                if events.crashed {
+                    self.audio.play(Sound::Hit);
+                    self.set_screen(ctx, Screen::Dying);
+                }
+                
+                if events.time_up {
+                    self.audio.play_sting();
+                    self.set_screen(ctx, Screen::GameOver);
+                }
+            }
+            Screen::Dying => {
+                // Whatever was pressed was taken above and is dropped here.
+                self.flap_queued = false;
+                self.renderer.play_death(ctx, &mut self.canvas, self.screen_ticks);
+                let grounded = self.state.fall();
+                let length = (self.state.config.death.length() * TICK_RATE) as u64;
+                if grounded && self.screen_ticks >= length {
+                    self.audio.play(Sound::Die);
+                    self.set_screen(ctx, Screen::GameOver);
+                }
+            }
+            Screen::GameOver => {
+                let flap = std::mem::take(&mut self.flap_queued);
+                if self.screen_ticks < GAME_OVER_INPUT_DELAY {
+                    return;
+                }
+                
+                if menu_step != 0 {
+                    self.selected_button = self.selected_button.cycle(menu_step);
+                    let overlay = self.game_over_overlay();
+                    self.renderer.show_overlay(ctx, &mut self.canvas, overlay);
+                }
+                
+                // A click on a button is also a flap, so the buttons go first.
+                let pressed = if clicked.is_some() {
+                    clicked
+                } else if flap || restart {
+                    Some(Button::Restart)
+                } else if pause {
+                    Some(Button::Menu)
+                } else if confirm {
+                    Some(self.selected_button)
+                } else {
+                    None
+                };
+                
+                match pressed {
+                    Some(Button::Restart) => self.set_screen(ctx, Screen::GetReady),
+                    Some(Button::Menu) => self.set_screen(ctx, Screen::Title),
+                    _ => {}
+                }
+            }
+            Screen::Paused => {
+                self.flap_queued = false;
+                if pause {
+                    self.set_screen(ctx, Screen::Playing);
+                } else if restart {
+                    self.state.end_run();
+                    self.set_screen(ctx, Screen::GameOver);
+                }
+            }
+            Screen::Settings => {
+                self.flap_queued = false;
+                if let Some(binding) = self.captured.take() {
+                    self.listening = false;
+                    self.rejected = None;
+                    if binding != Binding::Key("Escape".to_string()) {
+                        if self.input.bind(self.settings_action, binding.clone()) {
+                            self.bindings_changed();
+                        } else {
+                            self.rejected = Some(binding);
+                            self.listening = true;
+                        }
+                    }
+                    self.show_settings(ctx);
+                    return;
+                }
+                
+                if let Some(Button::Action(action)) = clicked {
+                    self.settings_action = action;
+                    self.listening = true;
+                } else if clicked == Some(Button::Menu) || pause || settings {
+                    self.set_screen(ctx, Screen::Title);
+                    return;
+                } else if menu_step != 0 {
+                    self.settings_action = self.settings_action.cycle(menu_step);
+                } else if confirm {
+                    self.listening = true;
+                } else if unbind && self.input.unbind_last(self.settings_action).is_some() {
+                    self.bindings_changed();
+                } else {
+                    return;
+                }
+                
+                self.rejected = None;
+                self.show_settings(ctx);
             }
         }
     }
     
-    fn handle_game_over(&mut self, ctx: &mut Context) {
-        println!("> GAME OVER!! Score: {}", self.score_manager.score);
-        
-        if let Some(bird) = self.canvas.get_game_object_mut("flappybird") {
-            bird.position = (200.0, 300.0);
-            bird.momentum = (0.0, 0.0);
+    fn show_settings(&mut self, ctx: &mut Context) {
+        let overlay = Overlay::Settings {
+            selected: self.settings_action,
+            listening: self.listening,
+            bindings: input::Action::ALL.map(|action| self.input.bindings(action).to_vec()),
+            rejected: self.rejected.clone(),
+        };
+        self.renderer.show_overlay(ctx, &mut self.canvas, overlay);
+    }
+    
+    /// Saves the bindings after a change.
+    fn bindings_changed(&mut self) {
+        if let Err(err) = GameConfig::save_input(&self.config_path, &self.input) {
+            eprintln!("Failed to save controls to {}: {}", self.config_path.display(), err);
         }
-        
-        self.pipe_manager.reset(&mut self.canvas);
-        self.score_manager.reset(ctx, &mut self.canvas);
-        
-        self.game_over = false;
     }
     
-    fn update_base_positions(&mut self) {
-        const BASE_SPEED: f32 = -3.0;
-        let total_width = self.base_width * 4.0;
-        
-        if let Some(base1) = self.canvas.get_game_object_mut("base1") {
-            base1.position.0 += BASE_SPEED;
-            if base1.position.0 < -self.base_width {
-                base1.position.0 += total_width;
+    fn music_cue(&self) -> MusicCue {
+        let score = self.state.score_manager.score;
+        match self.screen {
+            Screen::Title | Screen::Settings => MusicCue::Silent,
+            Screen::GetReady => MusicCue::Intro,
+            Screen::Playing => MusicCue::Playing { score },
+            Screen::Paused => MusicCue::Ducked { score },
+            Screen::Dying | Screen::GameOver => MusicCue::Ending,
+        }
+    }
+    
+    fn set_screen(&mut self, ctx: &mut Context, screen: Screen) {
+        self.exit_screen(self.screen);
+        self.screen = screen;
+        self.screen_ticks = 0;
+        self.enter_screen(ctx, screen);
+    }
+    
+    fn enter_screen(&mut self, ctx: &mut Context, screen: Screen) {
+        match screen {
+            Screen::Title => {
+                let overlay = self.title_overlay();
+                self.renderer.show_overlay(ctx, &mut self.canvas, overlay);
+            }
+            Screen::GameOver => {
+                self.handle_game_over();
+                self.selected_button = Button::Restart;
+                let overlay = self.game_over_overlay();
+                self.renderer.show_overlay(ctx, &mut self.canvas, overlay);
             }
+            Screen::Paused => self.renderer.show_overlay(ctx, &mut self.canvas, Overlay::Paused),
+            Screen::Settings => {
+                self.rejected = None;
+                self.show_settings(ctx);
+            }
+            Screen::Dying => self.audio.play_sting(),
+            Screen::GetReady | Screen::Playing => {}
         }
-        
-        if let Some(base2) = self.canvas.get_game_object_mut("base2") {
-            base2.position.0 += BASE_SPEED;
-            if base2.position.0 < -self.base_width {
-                base2.position.0 += total_width;
+    }
+    
+    fn title_overlay(&self) -> Overlay {
+        let mode = self.state.mode;
+        Overlay::Title { mode, best: self.high_scores.best(mode) }
+    }
+    
+    fn game_over_overlay(&self) -> Overlay {
+        let score = self.state.score_manager.score;
+        Overlay::GameOver {
+            score,
+            best: self.high_scores.best(self.state.mode).max(score),
+            medal: Medal::for_score(score, &self.state.config.medals),
+            selected: self.selected_button,
+            seed: self.state.seed(),
+        }
+    }
+    
+    /// Switches modes from the title menu. The pipe pool can grow with the
+    /// mode, so the scene is rebuilt around the fresh run.
+    fn select_mode(&mut self, ctx: &mut Context, mode: GameMode) {
+        self.state.set_mode(mode, self.state.seed());
+        self.restart();
+        let overlay = self.title_overlay();
+        self.renderer.show_overlay(ctx, &mut self.canvas, overlay);
+        self.renderer.build_scene(ctx, &mut self.canvas, &self.state);
+    }
+    
+    fn exit_screen(&mut self, screen: Screen) {
+        match screen {
+            Screen::Title | Screen::Paused => self.renderer.clear_overlay(&mut self.canvas),
+            Screen::Settings => {
+                self.listening = false;
+                self.renderer.clear_overlay(&mut self.canvas);
+            }
+            Screen::GameOver => {
+                self.renderer.clear_overlay(&mut self.canvas);
+                self.restart();
             }
+            Screen::Dying => self.renderer.end_death(),
+            Screen::GetReady | Screen::Playing => {}
         }
+    }
+    
+    /// Live flaps are recorded against the tick they land on; during
+    /// playback the recorded flaps are used and live input is dropped.
+    fn next_flap(&mut self) -> bool {
+        let live_flap = std::mem::take(&mut self.flap_queued);
         
-        if let Some(base3) = self.canvas.get_game_object_mut("base3") {
-            base3.position.0 += BASE_SPEED;
-            if base3.position.0 < -self.base_width {
-                base3.position.0 += total_width;
+        match &mut self.replay {
+            ReplayMode::Recording(replay) => {
+                if live_flap {
+                    replay.record_flap(self.state.tick);
+                }
+                live_flap
             }
+            ReplayMode::Playback(player) => player.flap_at(self.state.tick),
         }
-        
-        if let Some(base4) = self.canvas.get_game_object_mut("base4") {
-            base4.position.0 += BASE_SPEED;
-            if base4.position.0 < -self.base_width {
-                base4.position.0 += total_width;
+    }
+    
+    /// Same as `next_flap` for shots. Only shots in a mode with aliens are
+    /// recorded, everywhere else they do nothing anyway.
+    fn next_fire(&mut self, live_fire: bool) -> bool {
+        match &mut self.replay {
+            ReplayMode::Recording(replay) => {
+                let fire = live_fire && self.state.mode.has_aliens();
+                if fire {
+                    replay.record_fire(self.state.tick);
+                }
+                fire
             }
+            ReplayMode::Playback(player) => player.fire_at(self.state.tick),
         }
     }
+    
+    /// Records the finished run. Playing back a replay leaves the scores,
+    /// the wallet and the replay file alone.
+    fn handle_game_over(&mut self) {
+        if let ReplayMode::Recording(replay) = &mut self.replay {
+            replay.record_end(self.state.tick);
+            self.high_scores.record(self.state.score_manager.score, self.state.seed(), self.state.mode);
+            self.wallet.deposit(self.state.coin_manager.collected);
+            
+            if let Err(err) = replay.save(&self.record_path) {
+                eprintln!("Failed to save replay to {}: {}", self.record_path.display(), err);
+            }
+        }
+    }
+    
+    fn restart(&mut self) {
+        let seed = self.fixed_seed.unwrap_or_else(rand::random);
+        self.state.reset(seed);
+        self.replay = ReplayMode::Recording(Replay::new(seed, self.state.canvas_size, self.state.mode));
+        self.flap_queued = false;
+    }
 }
 
 pub struct MyApp;
 
 impl MyApp {
     fn new(ctx: &mut Context) -> impl Drawable {
-        let flappybird_width = 50.0;
-        let flappybird_height = 35.0;
-        let initial_size = (800.0, 600.0);
-        let pipe_width = 100.0 * 0.5;
-        let pipe_height = 800.0;
-        let gap_size = 220.0;
-        let base_height = 112.0;
-        let base_width = 336.0;
-
-        let bg_bytes = include_bytes!("../assets/bg.png");
-        let bg_img = image::load_from_memory(bg_bytes)
-            .expect("Failed to load background image");
-        let bg_image = bg_img.to_rgba8();
-        let background_image = Image {
-            shape: ShapeType::Rectangle(0.0, initial_size, 0.0),
-            image: bg_image.into(),
-            color: None
-        };
-
-        let flappybird_gif_bytes = include_bytes!("../assets/flappybird.gif");
-        let flappybird_animation = AnimatedSprite::new(
-            flappybird_gif_bytes,
-            (flappybird_width, flappybird_height),
-            12.0  
-        ).expect("Failed to load flappy bird animation");
-
-        let flappybird_image = flappybird_animation.get_current_image();
-
-        let base_bytes = include_bytes!("../assets/base.png");
-        let base_img = image::load_from_memory(base_bytes)
-            .expect("Failed to load base image");
-        let base_image = base_img.to_rgba8();
-
-        let mut stork_canvas = Canvas::new(ctx, initial_size);
-
-        let background = GameObject::new(
-            ctx,
-            "background".to_string(),
-            background_image,
-            initial_size.0.max(initial_size.1), 
-            (0.0, 0.0), 
-            vec!["background".to_string()],
-            (0.0, 0.0),
-            (1.0, 1.0), 
-            0.0,
-        );
-
-        stork_canvas.add_game_object("background".to_string(), background);
+        let options = LaunchOptions::from_env();
+        let seed = options.seed.unwrap_or_else(rand::random);
+        let config = GameConfig::load_or_default(&options.config);
+        let assets = Assets::new(options.assets.clone());
         
-        let flappybird = GameObject::new(
-            ctx,
-            "flappybird".to_string(),
-            flappybird_image,
-            flappybird_width.max(flappybird_height),
-            (200.0, 300.0),
-            vec![
-                "player".to_string(),
-                "flyingbird".to_string(),
-            ],
-            (0.0, 0.0),
-            (0.85, 0.85), 
-            0.30,
-        )
-        .with_animation(flappybird_animation);
-
-        stork_canvas.add_game_object("flappybird".to_string(), flappybird);
-
-        let base_y = initial_size.1 - base_height;
-        
-        let base1_img_obj = Image {
-            shape: ShapeType::Rectangle(0.0, (base_width, base_height), 0.0),
-            image: base_image.clone().into(),
-            color: None
-        };
+        let playback = options.replay.as_ref().and_then(|path| match Replay::load(path) {
+            Ok(replay) => Some(replay),
+            Err(err) => {
+                eprintln!("Failed to load replay {}: {}", path.display(), err);
+                None
+            }
+        });
         
-        let base2_img_obj = Image {
-            shape: ShapeType::Rectangle(0.0, (base_width, base_height), 0.0),
-            image: base_image.clone().into(),
-            color: None
-        };
-
-        let base1 = GameObject::new(
-            ctx,
-            "base1".to_string(),
-            base1_img_obj,
-            base_width.max(base_height),
-            (0.0, base_y),
-            vec!["ground".to_string(), "obstacle".to_string()],
-            (0.0, 0.0),
-            (1.0, 1.0),
-            0.0,
-        );
+        let mode = options.mode.unwrap_or_default();
+        let initial_size = config.layout.canvas_size;
+        let stork_canvas = Canvas::new(ctx, initial_size);
+        let renderer = Renderer::new(&config, &assets);
 
-        let base2 = GameObject::new(
-            ctx,
-            "base2".to_string(),
-            base2_img_obj,
-            base_width.max(base_height),
-            (base_width, base_y),
-            vec!["ground".to_string(), "obstacle".to_string()],
-            (0.0, 0.0),
-            (1.0, 1.0),
-            0.0,
-        );
-        
-        let base3_img_obj = Image {
-            shape: ShapeType::Rectangle(0.0, (base_width, base_height), 0.0),
-            image: base_image.clone().into(),
-            color: None
-        };
-        
-        let base3 = GameObject::new(
-            ctx,
-            "base3".to_string(),
-            base3_img_obj,
-            base_width.max(base_height),
-            (base_width * 2.0, base_y),
-            vec!["ground".to_string(), "obstacle".to_string()],
-            (0.0, 0.0),
-            (1.0, 1.0),
-            0.0,
-        );
-        
-        let base4_img_obj = Image {
-            shape: ShapeType::Rectangle(0.0, (base_width, base_height), 0.0),
-            image: base_image.clone().into(),
-            color: None
+        let screen = match playback {
+            Some(_) => Screen::GetReady,
+            None => Screen::Title,
         };
         
-        let base4 = GameObject::new(
-            ctx,
-            "base4".to_string(),
-            base4_img_obj,
-            base_width.max(base_height),
-            (base_width * 3.0, base_y),
-            vec!["ground".to_string(), "obstacle".to_string()],
-            (0.0, 0.0),
-            (1.0, 1.0),
-            0.0,
-        );
-
-        stork_canvas.add_game_object("base1".to_string(), base1);
-        stork_canvas.add_game_object("base2".to_string(), base2);
-        stork_canvas.add_game_object("base3".to_string(), base3);
-        stork_canvas.add_game_object("base4".to_string(), base4);
-
-        stork_canvas.add_event(
-            GameEvent::KeyPress {
-                key: Key::Character("w".to_string().into()),
-                action: Action::ApplyMomentum {  
-                    target: Target::ById("flappybird".to_string()),
-                    value: (0.0, -10.5)  
-                },
-                target: Target::ById("flappybird".to_string())
-            },
-            Target::ById("flappybird".to_string())
-        );
-
-        let pipe_manager = PipeManager::new(
-            pipe_width,
-            pipe_height,
-            gap_size,
-            initial_size,
-        );
-
-        let score_manager = ScoreManager::new(initial_size);
-
         let mut game = Game { 
-            canvas: stork_canvas,
-            canvas_size: initial_size,
-            pipe_manager,
-            score_manager,
-            base_width,
-            game_over: false,
+            canvas: InputCanvas::new(stork_canvas),
+            canvas_size: Cell::new(initial_size),
+            state: match &playback {
+                Some(replay) => GameState::new(&config, replay.canvas_size, replay.seed, replay.mode),
+                None => GameState::new(&config, initial_size, seed, mode),
+            },
+            renderer,
+            audio: Audio::new(config.audio),
+            high_scores: HighScoreStore::open_default(),
+            wallet: WalletStore::open_default(),
+            timestep: FixedTimestep::new(),
+            screen,
+            screen_ticks: 0,
+            input: config.input.clone(),
+            config_path: options.config.clone(),
+            flap_queued: false,
+            fire_queued: false,
+            pause_queued: false,
+            restart_queued: false,
+            menu_step: 0,
+            confirm_queued: false,
+            unbind_queued: false,
+            settings_queued: false,
+            click_queued: None,
+            selected_button: Button::Restart,
+            settings_action: input::Action::Flap,
+            listening: false,
+            captured: None,
+            rejected: None,
+            fixed_seed: options.seed,
+            replay: match playback {
+                Some(replay) => ReplayMode::Playback(ReplayPlayer::new(replay)),
+                None => ReplayMode::Recording(Replay::new(seed, initial_size, mode)),
+            },
+            record_path: options.record,
         };
 
         game.canvas.add_event(
             GameEvent::Collision {
                 action: Action::ApplyMomentum {
-                    target: Target::ById("flappybird".to_string()),
+                    target: Target::ByTag("player".to_string()),
                     value: (0.0, 0.0)
                 },
                 target: Target::ByTag("obstacle".to_string())
             },
-            Target::ById("flappybird".to_string())
+            Target::ByTag("player".to_string())
         );
+        
+        game.renderer.build_scene(ctx, &mut game.canvas, &game.state);
+        game.enter_screen(ctx, screen);
 
         game
     }
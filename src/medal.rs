@@ -0,0 +1,22 @@
+use crate::config::MedalConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Medal {
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+}
+
+impl Medal {
+    pub const ALL: [Medal; 4] = [Medal::Bronze, Medal::Silver, Medal::Gold, Medal::Platinum];
+
+    /// The best medal `score` earns, None below the bronze threshold.
+    pub fn for_score(score: usize, config: &MedalConfig) -> Option<Medal> {
+        Self::ALL.into_iter()
+            .zip(config.thresholds)
+            .rev()
+            .find(|(_, threshold)| score >= *threshold)
+            .map(|(medal, _)| medal)
+    }
+}
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+
+use crate::difficulty::Difficulty;
+use crate::timestep::TICK_RATE;
+
+const TIME_ATTACK_SECONDS: f32 = 60.0;
+
+/// The rule set a run is played with. Every mode keeps its own high score
+/// table, keyed by `name`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    #[default]
+    Classic,
+    /// Wider gaps and slower pipes.
+    Easy,
+    /// Narrow gaps, and every pair moves from the start.
+    Hard,
+    /// Nothing kills the bird. It lands on the ground and flies through pipes.
+    Zen,
+    /// As many pipes as possible in `TIME_ATTACK_SECONDS`.
+    TimeAttack,
+    /// The bird fires at aliens flying in between the pipes.
+    Shooter,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 6] = [
+        GameMode::Classic,
+        GameMode::Easy,
+        GameMode::Hard,
+        GameMode::Zen,
+        GameMode::TimeAttack,
+        GameMode::Shooter,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "classic",
+            GameMode::Easy => "easy",
+            GameMode::Hard => "hard",
+            GameMode::Zen => "zen",
+            GameMode::TimeAttack => "time_attack",
+            GameMode::Shooter => "shooter",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    pub fn index(&self) -> usize {
+        Self::ALL.iter().position(|mode| mode == self).unwrap_or(0)
+    }
+
+    /// The mode `steps` places further along `ALL`, wrapping at both ends.
+    pub fn cycle(&self, steps: i32) -> Self {
+        let len = Self::ALL.len() as i32;
+        Self::ALL[(self.index() as i32 + steps).rem_euclid(len) as usize]
+    }
+
+    /// Bends the difficulty curve to the mode.
+    pub fn adjust(&self, difficulty: Difficulty) -> Difficulty {
+        let (speed, gap) = match self {
+            GameMode::Easy => (0.8, 1.25),
+            GameMode::Hard => (1.1, 0.85),
+            // A little extra room to line up shots.
+            GameMode::Shooter => (1.0, 1.1),
+            GameMode::Classic | GameMode::Zen | GameMode::TimeAttack => (1.0, 1.0),
+        };
+
+        Difficulty {
+            world_speed: difficulty.world_speed * speed,
+            gap_size: difficulty.gap_size * gap,
+            ..difficulty
+        }
+    }
+
+    pub fn can_die(&self) -> bool {
+        *self != GameMode::Zen
+    }
+
+    pub fn has_aliens(&self) -> bool {
+        *self == GameMode::Shooter
+    }
+
+    pub fn moving_pipes(&self) -> bool {
+        *self == GameMode::Hard
+    }
+
+    pub fn time_limit(&self) -> Option<u64> {
+        match self {
+            GameMode::TimeAttack => Some((TIME_ATTACK_SECONDS * TICK_RATE) as u64),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use std::path::PathBuf;
+
+const APP_DIR: &str = "rampy-bird";
+
+/// Per-user directory for saves and asset overrides, e.g.
+/// `~/.local/share/rampy-bird` on Linux.
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_DIR))
+}
+
+pub fn default_asset_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("assets"))
+}
+
+/// `name` in the data directory, or in the working directory on platforms
+/// without one.
+pub fn data_file(name: &str) -> PathBuf {
+    data_dir().map(|dir| dir.join(name)).unwrap_or_else(|| PathBuf::from(name))
+}
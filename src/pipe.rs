@@ -1,144 +1,492 @@
-use prism::Context;
-use prism::canvas::{Image, ShapeType};
-use stork::{Canvas, GameObject};
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
+use crate::collision::{Obb, Rect};
+use crate::config::{self, GameConfig, LayoutConfig, MotionConfig, SpawnConfig};
+use crate::difficulty::Difficulty;
+use crate::mode::GameMode;
+use crate::timestep::{TICK_DT, TICK_RATE};
+
+/// Where a sliding gap settles, as a fraction of the canvas width. It is
+/// always done moving well before it reaches the bird.
+const SLIDE_END: f32 = 0.5;
+
+/// How a pair's gap changes while it crosses the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PipeMotion {
+    Static,
+    /// The gap bobs on a sine wave around where it spawned.
+    Bob,
+    /// The gap glides to `to_y` partway across the screen.
+    Slide { to_y: f32 },
+    /// The gap opens and closes around its center.
+    Breathe,
+}
+
+/// One slot of the pipe pool. Slots are recycled, so `id` is handed out
+/// fresh every time a slot is reused and tells pipes apart over a run.
+#[derive(Debug, Clone)]
+pub struct PipePair {
+    pub id: u32,
+    pub x: f32,
+    pub gap_y: f32,
+    pub gap_size: f32,
+    /// Where the gap spawned. Moving gaps bob around it.
+    pub anchor_y: f32,
+    /// The gap's full size, breathing gaps shrink from it.
+    pub base_gap_size: f32,
+    pub motion: PipeMotion,
+    /// Ticks since the pair spawned.
+    pub age: u32,
+    pub active: bool,
+}
+
+impl PipePair {
+    pub fn top_position(&self, pipe_height: f32) -> (f32, f32) {
+        (self.x, self.gap_y - self.gap_size / 2.0 - pipe_height)
+    }
+
+    pub fn bottom_position(&self) -> (f32, f32) {
+        (self.x, self.gap_y + self.gap_size / 2.0)
+    }
+
+    /// Moves the gap for this tick. Gaps are kept inside `gap_range`, so
+    /// whatever they do they stay passable.
+    fn apply_motion(&mut self, motion: &MotionConfig, gap_range: (f32, f32), canvas_width: f32) {
+        let phase = self.age as f32;
+        match self.motion {
+            PipeMotion::Static => {}
+            PipeMotion::Bob => {
+                let offset = (phase * motion.bob_speed + self.id as f32).sin() * motion.bob_amplitude;
+                self.gap_y = self.anchor_y + offset;
+            }
+            PipeMotion::Slide { to_y } => {
+                let end_x = canvas_width * SLIDE_END;
+                let progress = ((end_x + motion.slide_length - self.x) / motion.slide_length).clamp(0.0, 1.0);
+                let eased = progress * progress * (3.0 - 2.0 * progress);
+                self.gap_y = self.anchor_y + (to_y - self.anchor_y) * eased;
+            }
+            PipeMotion::Breathe => {
+                let open = 0.5 + 0.5 * (phase * motion.breathe_speed).cos();
+                let scale = motion.breathe_min_scale + (1.0 - motion.breathe_min_scale) * open;
+                self.gap_size = self.base_gap_size * scale;
+            }
+        }
+        self.gap_y = self.gap_y.clamp(gap_range.0, gap_range.1);
+    }
+
+    pub fn rects(&self, pipe_width: f32, pipe_height: f32) -> [Rect; 2] {
+        let top = self.top_position(pipe_height);
+        let bottom = self.bottom_position();
+        [
+            Rect::new(top.0, top.1, pipe_width, pipe_height),
+            Rect::new(bottom.0, bottom.1, pipe_width, pipe_height),
+        ]
+    }
+}
+
+/// Moves pipes across the screen using a fixed pool of pipe pairs. The pool
+/// is sized so every pair that can be on screen at once has a slot, and
+/// slots are reused in place instead of being allocated per spawn.
 #[derive(Debug)]
 pub struct PipeManager {
     pub pipe_spawn_timer: f32,
     pub pipe_counter: u32,
-    pub toppipe_image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
-    pub bottompipe_image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    pool: Vec<PipePair>,
     pub pipe_width: f32,
     pub pipe_height: f32,
     pub gap_size: f32,
+    pub seed: u64,
+    rng: StdRng,
+    speed: f32,
+    /// Slows the pipes and their spawning down together, so the spacing
+    /// between pairs stays the same.
+    time_scale: f32,
+    /// Closest two pairs can get anywhere on the difficulty curve.
+    min_spacing: f32,
+    mode: GameMode,
+    motion: MotionConfig,
+    /// Whether new pairs may pick a motion other than `Static`.
+    motion_enabled: bool,
+    spawning: SpawnConfig,
+    layout: LayoutConfig,
     canvas_size: (f32, f32),
 }
 
 impl PipeManager {
     pub fn new(
-        pipe_width: f32,
-        pipe_height: f32,
-        gap_size: f32,
+        config: &GameConfig,
         canvas_size: (f32, f32),
+        seed: u64,
+        mode: GameMode,
     ) -> Self {
-        let toppipe_bytes = include_bytes!("../assets/toppipe.png");
-        let toppipe_img = image::load_from_memory(toppipe_bytes)
-            .expect("Failed to load toppipe image");
-        let toppipe_image = toppipe_img.to_rgba8();
-        
-        let bottompipe_bytes = include_bytes!("../assets/bottompipe.png");
-        let bottompipe_img = image::load_from_memory(bottompipe_bytes)
-            .expect("Failed to load bottompipe image");
-        let bottompipe_image = bottompipe_img.to_rgba8();
-
-        Self {
+        let mut manager = Self {
             pipe_spawn_timer: 0.0,
             pipe_counter: 0,
-            toppipe_image,
-            bottompipe_image,
-            pipe_width,
-            pipe_height,
-            gap_size,
+            pool: Vec::new(),
+            pipe_width: config.layout.pipe_width,
+            pipe_height: config.layout.pipe_height,
+            gap_size: config.spawning.gap_size,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            speed: config.physics.world_speed,
+            time_scale: 1.0,
+            min_spacing: min_spacing(config, mode),
+            mode,
+            motion: config.motion,
+            motion_enabled: false,
+            spawning: config.spawning,
+            layout: config.layout,
             canvas_size,
+        };
+        manager.fill_pool();
+        manager
+    }
+
+    /// Every slot, active or not, in a stable order.
+    pub fn pool(&self) -> &[PipePair] {
+        &self.pool
+    }
+
+    pub fn active_pipes(&self) -> impl Iterator<Item = &PipePair> {
+        self.pool.iter().filter(|pipe| pipe.active)
+    }
+
+    /// Pairs that can be on screen at once: the distance a pipe travels from
+    /// spawning to being retired, divided by the tightest spacing between
+    /// spawns the difficulty curve allows.
+    fn pool_capacity(&self) -> usize {
+        let travel = self.canvas_size.0 + self.spawning.spawn_offset + self.pipe_width + self.spawning.despawn_margin;
+        (travel / self.min_spacing).ceil() as usize + 1
+    }
+
+    /// Pixels the pipes move this tick, with the time scale applied.
+    pub fn speed(&self) -> f32 {
+        self.speed * self.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale;
+    }
+
+    /// Switches to new pipe settings, bent to the current mode. Pipes already
+    /// out keep their gap, the new speed applies to all of them.
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        let difficulty = self.mode.adjust(difficulty);
+        self.speed = difficulty.world_speed;
+        self.spawning.interval = difficulty.interval;
+        self.spawning.gap_size = difficulty.gap_size;
+        self.gap_size = difficulty.gap_size;
+    }
+
+    /// Changes the rules for the next run. Call `reset` and `set_difficulty`
+    /// afterwards, pipes already out are left as they are.
+    pub fn set_mode(&mut self, config: &GameConfig, mode: GameMode) {
+        self.mode = mode;
+        self.min_spacing = min_spacing(config, mode);
+        self.fill_pool();
+    }
+
+    /// Grows the pool to `pool_capacity`. It never shrinks, so slots already
+    /// handed to the renderer stay valid.
+    fn fill_pool(&mut self) {
+        let capacity = self.pool_capacity();
+        while self.pool.len() < capacity {
+            self.pool.push(PipePair {
+                id: 0,
+                x: 0.0,
+                gap_y: 0.0,
+                gap_size: self.gap_size,
+                anchor_y: 0.0,
+                base_gap_size: self.gap_size,
+                motion: PipeMotion::Static,
+                age: 0,
+                active: false,
+            });
         }
     }
 
-    pub fn update(&mut self, ctx: &mut Context, canvas: &mut Canvas) {
-        const PIPE_SPAWN_INTERVAL: f32 = 2.0;
-        
-        self.pipe_spawn_timer += 0.016;
-        
-        if self.pipe_spawn_timer >= PIPE_SPAWN_INTERVAL {
-            self.spawn_pipe_pair(ctx, canvas);
+    pub fn update(&mut self) {
+        self.pipe_spawn_timer += TICK_DT * self.time_scale;
+
+        if self.pipe_spawn_timer >= self.spawning.interval {
+            self.spawn_pipe_pair();
             self.pipe_spawn_timer = 0.0;
         }
-        
-        self.remove_offscreen_pipes(canvas);
-    }
-
-    pub fn spawn_pipe_pair(&mut self, ctx: &mut Context, canvas: &mut Canvas) {
-        let mut rng = rand::thread_rng();
-        
-        let base_height = 112.0;
-        let min_gap_y = 150.0;
-        let max_gap_y = self.canvas_size.1 - base_height - self.gap_size / 2.0 - 10.0;
-        let gap_y = rng.gen_range(min_gap_y..max_gap_y);
-        
-        let toppipe_img_obj = Image {
-            shape: ShapeType::Rectangle(0.0, (self.pipe_width, self.pipe_height), 0.0),
-            image: self.toppipe_image.clone().into(),
-            color: None
+
+        let gap_range = self.gap_range();
+        let speed = self.speed();
+        for pipe in self.pool.iter_mut().filter(|pipe| pipe.active) {
+            pipe.x += speed;
+            pipe.age += 1;
+            pipe.apply_motion(&self.motion, gap_range, self.canvas_size.0);
+        }
+
+        self.remove_offscreen_pipes();
+    }
+
+    /// Range the gap centers are drawn from. On a canvas too short for the
+    /// configured margins it collapses to `min_gap_y`.
+    pub fn gap_range(&self) -> (f32, f32) {
+        let min_gap_y = self.spawning.min_gap_y;
+        let max_gap_y = config::max_gap_y(&self.spawning, &self.layout, self.canvas_size.1);
+        (min_gap_y, max_gap_y.max(min_gap_y))
+    }
+
+    pub fn spawn_pipe_pair(&mut self) {
+        let gap_y = self.random_gap_y();
+        let motion = self.pick_motion();
+
+        // The pool is sized so a free slot always exists, but should the
+        // spawn spacing ever shrink below what it was sized for, recycling
+        // the leftmost pair beats dropping the new one.
+        let slot = match self.pool.iter().position(|pipe| !pipe.active) {
+            Some(slot) => slot,
+            None => self.pool.iter().enumerate()
+                .min_by(|(_, a), (_, b)| a.x.total_cmp(&b.x))
+                .map(|(slot, _)| slot)
+                .expect("the pipe pool always has at least one slot"),
         };
-        
-        let bottompipe_img_obj = Image {
-            shape: ShapeType::Rectangle(0.0, (self.pipe_width, self.pipe_height), 0.0),
-            image: self.bottompipe_image.clone().into(),
-            color: None
+
+        self.pool[slot] = PipePair {
+            id: self.pipe_counter,
+            x: self.canvas_size.0 + self.spawning.spawn_offset,
+            gap_y,
+            gap_size: self.gap_size,
+            anchor_y: gap_y,
+            base_gap_size: self.gap_size,
+            motion,
+            age: 0,
+            active: true,
         };
-        
-        let toppipe = GameObject::new_rect(
-            ctx,
-            format!("toppipe_{}", self.pipe_counter),
-            toppipe_img_obj,
-            (self.pipe_width, self.pipe_height),
-            (self.canvas_size.0 + 100.0, gap_y - self.gap_size / 2.0 - self.pipe_height),
-            vec!["pipe".to_string(), "obstacle".to_string()],
-            (-3.0, 0.0),
-            (1.0, 1.0),
-            0.0,
-        );
-        
-        let bottompipe = GameObject::new_rect(
-            ctx,
-            format!("bottompipe_{}", self.pipe_counter),
-            bottompipe_img_obj,
-            (self.pipe_width, self.pipe_height),
-            (self.canvas_size.0 + 100.0, gap_y + self.gap_size / 2.0),
-            vec!["pipe".to_string(), "obstacle".to_string()],
-            (-3.0, 0.0),
-            (1.0, 1.0),
-            0.0,
-        );
-        
-        canvas.add_game_object(format!("toppipe_{}", self.pipe_counter), toppipe);
-        canvas.add_game_object(format!("bottompipe_{}", self.pipe_counter), bottompipe);
-        
+
         self.pipe_counter += 1;
     }
 
-    pub fn remove_offscreen_pipes(&mut self, canvas: &mut Canvas) {
-        let mut pipes_to_remove: Vec<String> = Vec::new();
-        
-        for i in 0..self.pipe_counter {
-            let toppipe_name = format!("toppipe_{}", i);
-            let bottompipe_name = format!("bottompipe_{}", i);
-            
-            if let Some(obj) = canvas.get_game_object(&toppipe_name) {
-                if obj.position.0 < -self.pipe_width - 50.0 {
-                    pipes_to_remove.push(toppipe_name);
-                }
+    fn random_gap_y(&mut self) -> f32 {
+        let (min_gap_y, max_gap_y) = self.gap_range();
+        if max_gap_y > min_gap_y {
+            self.rng.random_range(min_gap_y..max_gap_y)
+        } else {
+            min_gap_y
+        }
+    }
+
+    /// Rolls the motion for a new pair from `motion.weights`. Nothing is
+    /// drawn from the rng while motions are off, so runs that never reach
+    /// `motion.from_score` get exactly the gaps they always did.
+    fn pick_motion(&mut self) -> PipeMotion {
+        if !self.motion_enabled {
+            return PipeMotion::Static;
+        }
+
+        let mut weights = self.motion.weights;
+        if self.mode.moving_pipes() {
+            weights[0] = 0.0;
+        }
+
+        let total: f32 = weights.iter().sum();
+        if total <= 0.0 {
+            return PipeMotion::Static;
+        }
+
+        let mut roll = self.rng.random_range(0.0..total);
+        let picked = weights.iter().position(|weight| {
+            if roll < *weight {
+                return true;
             }
-            
-            if let Some(obj) = canvas.get_game_object(&bottompipe_name) {
-                if obj.position.0 < -self.pipe_width - 50.0 {
-                    pipes_to_remove.push(bottompipe_name);
-                }
+            roll -= weight;
+            false
+        });
+
+        match picked {
+            Some(1) => PipeMotion::Bob,
+            Some(2) => PipeMotion::Slide { to_y: self.random_gap_y() },
+            Some(3) => PipeMotion::Breathe,
+            _ => PipeMotion::Static,
+        }
+    }
+
+    /// Lets new pairs move once the run is far enough along. Pairs already
+    /// out keep the motion they spawned with.
+    pub fn update_motion(&mut self, score: usize) {
+        self.motion_enabled = self.mode.moving_pipes() || score >= self.motion.from_score;
+    }
+
+    pub fn remove_offscreen_pipes(&mut self) {
+        let min_x = -self.pipe_width - self.spawning.despawn_margin;
+        for pipe in &mut self.pool {
+            if pipe.x < min_x {
+                pipe.active = false;
             }
         }
-        
-        for name in pipes_to_remove {
-            canvas.remove_game_object(&name);
+    }
+
+    pub fn collides_with(&self, hitbox: &Obb) -> bool {
+        self.active_pipes().any(|pipe| {
+            pipe.rects(self.pipe_width, self.pipe_height).iter().any(|rect| hitbox.intersects(rect))
+        })
+    }
+
+    pub fn resize(&mut self, canvas_size: (f32, f32)) {
+        self.canvas_size = canvas_size;
+        self.fill_pool();
+
+        let (min_gap_y, max_gap_y) = self.gap_range();
+        for pipe in self.pool.iter_mut().filter(|pipe| pipe.active) {
+            pipe.gap_y = pipe.gap_y.clamp(min_gap_y, max_gap_y);
+            pipe.anchor_y = pipe.anchor_y.clamp(min_gap_y, max_gap_y);
         }
     }
 
-    pub fn reset(&mut self, canvas: &mut Canvas) {
-        for i in 0..self.pipe_counter {
-            canvas.remove_game_object(&format!("toppipe_{}", i));
-            canvas.remove_game_object(&format!("bottompipe_{}", i));
+    /// Clears every pipe and restarts the gap sequence from `seed`, so the
+    /// same seed always produces the same run.
+    pub fn reset(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+        for pipe in &mut self.pool {
+            pipe.active = false;
         }
-        
         self.pipe_counter = 0;
         self.pipe_spawn_timer = 0.0;
+        self.time_scale = 1.0;
+    }
+}
+
+/// Closest two pairs can get anywhere on the difficulty curve in `mode`.
+fn min_spacing(config: &GameConfig, mode: GameMode) -> f32 {
+    Difficulty::corners(config)
+        .map(|difficulty| mode.adjust(difficulty).spacing(TICK_RATE))
+        .fold(f32::INFINITY, f32::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: u64 = 7;
+    const CANVAS: (f32, f32) = (800.0, 600.0);
+
+    fn manager(config: &GameConfig, mode: GameMode) -> PipeManager {
+        let mut manager = PipeManager::new(config, CANVAS, SEED, mode);
+        manager.set_difficulty(Difficulty::base(config));
+        manager
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn pool_keeps_its_size_over_a_long_run() {
+        let config = GameConfig::default();
+        let mut manager = manager(&config, GameMode::Classic);
+        let capacity = manager.pool_capacity();
+        assert_eq!(manager.pool().len(), capacity);
+
+        let min_x = -manager.pipe_width - config.spawning.despawn_margin;
+        for score in [0, 35, 80] {
+            manager.set_difficulty(Difficulty::at(&config, score));
+            for _ in 0..60 * 60 {
+                let before = manager.active_pipes().map(|pipe| (pipe.id, pipe.x)).collect::<Vec<_>>();
+                manager.update();
+                // Only pipes that left the screen give up their slot, nothing
+                // still in view is recycled.
+                for (id, x) in before {
+                    if x + manager.speed() >= min_x {
+                        assert!(manager.active_pipes().any(|pipe| pipe.id == id), "pipe {} vanished at x {}", id, x);
+                    }
+                }
+            }
+        }
+
+        assert_eq!(manager.pool().len(), capacity);
+        assert!(manager.pipe_counter as usize > capacity * 10);
+    }
+
+    #[test]
+    fn a_full_pool_recycles_the_leftmost_pair() {
+        let config = GameConfig::default();
+        let mut manager = manager(&config, GameMode::Classic);
+        let capacity = manager.pool().len();
+
+        for _ in 0..capacity {
+            manager.spawn_pipe_pair();
+            for pipe in &mut manager.pool {
+                pipe.x -= 10.0;
+            }
+        }
+        assert_eq!(manager.active_pipes().count(), capacity);
+
+        manager.spawn_pipe_pair();
+        assert_eq!(manager.pool().len(), capacity);
+        let mut ids = manager.active_pipes().map(|pipe| pipe.id).collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, (1..=capacity as u32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn switching_modes_grows_the_pool_for_the_tighter_spacing() {
+        // Easy's slower pipes sit closer together, so more fit on screen.
+        let config = GameConfig::default();
+        let mut manager = manager(&config, GameMode::Hard);
+        let hard = manager.pool().len();
+
+        manager.set_mode(&config, GameMode::Easy);
+        assert!(manager.pool().len() > hard);
+        assert_eq!(manager.pool().len(), manager.pool_capacity());
+
+        let easy = manager.pool().len();
+        manager.set_mode(&config, GameMode::Hard);
+        assert_eq!(manager.pool().len(), easy, "the pool never shrinks");
+    }
+
+    #[test]
+    fn moving_gaps_stay_inside_the_gap_range() {
+        let mut config = GameConfig::default();
+        // Far more than the canvas has room for, so the clamp does the work.
+        config.motion.bob_amplitude = 400.0;
+        let mut manager = manager(&config, GameMode::Hard);
+        manager.update_motion(0);
+        let (min_gap_y, max_gap_y) = manager.gap_range();
+
+        let mut seen = Vec::new();
+        for _ in 0..60 * 120 {
+            manager.update();
+            for pipe in manager.active_pipes() {
+                assert!((min_gap_y..=max_gap_y).contains(&pipe.gap_y), "{:?} left the gap range", pipe);
+                assert!(pipe.gap_size >= pipe.base_gap_size * config.motion.breathe_min_scale - 1e-3);
+                assert!(pipe.gap_size <= pipe.base_gap_size + 1e-3);
+                if let PipeMotion::Slide { to_y } = pipe.motion {
+                    assert!((min_gap_y..=max_gap_y).contains(&to_y));
+                }
+                let kind = std::mem::discriminant(&pipe.motion);
+                if !seen.contains(&kind) {
+                    seen.push(kind);
+                }
+            }
+        }
+
+        assert!(!seen.contains(&std::mem::discriminant(&PipeMotion::Static)), "hard mode moves every pair");
+        assert_eq!(seen.len(), 3);
+    }
+
+    #[test]
+    fn classic_gaps_stay_still_until_motion_starts() {
+        let config = GameConfig::default();
+        let mut manager = manager(&config, GameMode::Classic);
+        manager.update_motion(config.motion.from_score - 1);
+
+        for _ in 0..60 * 60 {
+            manager.update();
+            for pipe in manager.active_pipes() {
+                assert_eq!(pipe.motion, PipeMotion::Static);
+                assert_eq!(pipe.gap_y, pipe.anchor_y);
+                assert_eq!(pipe.gap_size, pipe.base_gap_size);
+            }
+        }
+
+        manager.update_motion(config.motion.from_score);
+        let spawned = manager.pipe_counter;
+        for _ in 0..60 * 60 {
+            manager.update();
+        }
+        assert!(manager.active_pipes().any(|pipe| pipe.id >= spawned && pipe.motion != PipeMotion::Static));
+    }
+}
@@ -0,0 +1,356 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::collision::{Obb, Rect};
+use crate::config::PowerUpConfig;
+use crate::pipe::PipeManager;
+use crate::timestep::TICK_RATE;
+
+/// Same idea as the coin salt, a random sequence of their own for a seed.
+const POWER_UP_SEED_SALT: u64 = 0x706f_7765_725f_7570;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUpKind {
+    /// Soaks up one hit from a pipe or the ground.
+    Shield,
+    /// Slows the world down, the bird keeps its own speed.
+    SlowMotion,
+    /// Shrinks the bird's hitbox.
+    Shrink,
+    /// Pulls nearby coins in.
+    Magnet,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 4] = [
+        PowerUpKind::Shield,
+        PowerUpKind::SlowMotion,
+        PowerUpKind::Shrink,
+        PowerUpKind::Magnet,
+    ];
+
+    pub fn index(&self) -> usize {
+        Self::ALL.iter().position(|kind| kind == self).unwrap_or(0)
+    }
+}
+
+/// A power-up waiting in a gap. `position` is the top-left corner.
+#[derive(Debug, Clone)]
+pub struct PowerUpPickup {
+    pub position: (f32, f32),
+    pub kind: PowerUpKind,
+    pub pipe_id: u32,
+    pub active: bool,
+}
+
+/// Spawns power-ups in the gaps and keeps the timers of the ones the bird
+/// picked up. Every kind has its own timer, so different kinds run side by
+/// side. Picking up a kind that is already running adds another
+/// `duration` to it, up to `max_stack` durations in total.
+#[derive(Debug)]
+pub struct PowerUps {
+    pool: Vec<PowerUpPickup>,
+    /// Ticks left on each kind, indexed like `PowerUpKind::ALL`.
+    ticks_left: [u64; 4],
+    /// Ticks the bird can't be hurt for after its shield broke.
+    grace_ticks: u64,
+    /// Id of the first pipe pair that hasn't been offered a power-up yet.
+    next_pipe: u32,
+    rng: StdRng,
+    config: PowerUpConfig,
+}
+
+impl PowerUps {
+    pub fn new(config: PowerUpConfig, seed: u64) -> Self {
+        Self {
+            pool: Vec::new(),
+            ticks_left: [0; 4],
+            grace_ticks: 0,
+            next_pipe: 0,
+            rng: StdRng::seed_from_u64(seed ^ POWER_UP_SEED_SALT),
+            config,
+        }
+    }
+
+    /// Every slot, active or not, in a stable order.
+    pub fn pool(&self) -> &[PowerUpPickup] {
+        &self.pool
+    }
+
+    pub fn size(&self) -> f32 {
+        self.config.size
+    }
+
+    /// Runs the timers down, offers every new pair a power-up and keeps the
+    /// pickups centered in their gaps. Call after `PipeManager::update`.
+    pub fn update(&mut self, pipe_manager: &PipeManager) {
+        for ticks in &mut self.ticks_left {
+            *ticks = ticks.saturating_sub(1);
+        }
+        self.grace_ticks = self.grace_ticks.saturating_sub(1);
+
+        while self.pool.len() < pipe_manager.pool().len() {
+            self.pool.push(PowerUpPickup {
+                position: (0.0, 0.0),
+                kind: PowerUpKind::Shield,
+                pipe_id: 0,
+                active: false,
+            });
+        }
+
+        while self.next_pipe < pipe_manager.pipe_counter {
+            self.offer(self.next_pipe);
+            self.next_pipe += 1;
+        }
+
+        let half = self.config.size / 2.0;
+        for pickup in self.pool.iter_mut().filter(|pickup| pickup.active) {
+            match pipe_manager.active_pipes().find(|pipe| pipe.id == pickup.pipe_id) {
+                Some(pipe) => pickup.position = (pipe.x + pipe_manager.pipe_width / 2.0 - half, pipe.gap_y - half),
+                None => pickup.active = false,
+            }
+        }
+    }
+
+    /// Both rolls are made for every pair, whatever the settings, like
+    /// `CoinManager::offer_coins`. The position is filled in by `update`.
+    fn offer(&mut self, pipe_id: u32) {
+        let spawn = self.rng.random_bool(f64::from(self.config.chance));
+        let kind = PowerUpKind::ALL[self.rng.random_range(0..PowerUpKind::ALL.len())];
+        if !self.config.enabled || !spawn {
+            return;
+        }
+
+        if let Some(pickup) = self.pool.iter_mut().find(|pickup| !pickup.active) {
+            *pickup = PowerUpPickup { position: (0.0, 0.0), kind, pipe_id, active: true };
+        }
+    }
+
+    /// Pipe pairs holding a power-up. Their gap has no room for a coin.
+    pub fn occupied_gaps(&self) -> impl Iterator<Item = u32> + '_ {
+        self.pool.iter().filter(|pickup| pickup.active).map(|pickup| pickup.pipe_id)
+    }
+
+    /// Picks up whatever power-up touches `hitbox` and starts it.
+    pub fn collect(&mut self, hitbox: &Obb) -> Option<PowerUpKind> {
+        let size = self.config.size;
+        let pickup = self.pool.iter_mut().find(|pickup| {
+            pickup.active && hitbox.intersects(&Rect::new(pickup.position.0, pickup.position.1, size, size))
+        })?;
+        pickup.active = false;
+
+        let kind = pickup.kind;
+        self.activate(kind);
+        Some(kind)
+    }
+
+    pub fn activate(&mut self, kind: PowerUpKind) {
+        let duration = self.duration_ticks();
+        let ticks = &mut self.ticks_left[kind.index()];
+        *ticks = (*ticks + duration).min(duration * u64::from(self.config.max_stack));
+    }
+
+    fn duration_ticks(&self) -> u64 {
+        (self.config.duration * TICK_RATE) as u64
+    }
+
+    pub fn ticks_left(&self, kind: PowerUpKind) -> u64 {
+        self.ticks_left[kind.index()]
+    }
+
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.ticks_left(kind) > 0
+    }
+
+    /// Running power-ups and their ticks left, in `PowerUpKind::ALL` order.
+    pub fn running(&self) -> impl Iterator<Item = (PowerUpKind, u64)> + '_ {
+        PowerUpKind::ALL.into_iter()
+            .map(|kind| (kind, self.ticks_left(kind)))
+            .filter(|(_, ticks)| *ticks > 0)
+    }
+
+    /// Uses up the shield, if there is one, and starts a short grace period
+    /// so the bird can get clear of whatever it hit.
+    pub fn break_shield(&mut self) -> bool {
+        if !self.is_active(PowerUpKind::Shield) {
+            return false;
+        }
+
+        self.ticks_left[PowerUpKind::Shield.index()] = 0;
+        self.grace_ticks = (self.config.shield_grace * TICK_RATE) as u64;
+        true
+    }
+
+    pub fn invulnerable(&self) -> bool {
+        self.grace_ticks > 0
+    }
+
+    /// What the world speed is multiplied by.
+    pub fn time_scale(&self) -> f32 {
+        if self.is_active(PowerUpKind::SlowMotion) { self.config.slow_motion_scale } else { 1.0 }
+    }
+
+    /// What the bird's hitbox is scaled by on top of `physics.hitbox_scale`.
+    pub fn hitbox_scale(&self) -> f32 {
+        if self.is_active(PowerUpKind::Shrink) { self.config.shrink_scale } else { 1.0 }
+    }
+
+    /// Radius and speed coins are pulled in with, while the magnet runs.
+    pub fn magnet(&self) -> Option<(f32, f32)> {
+        self.is_active(PowerUpKind::Magnet).then_some((self.config.magnet_radius, self.config.magnet_pull))
+    }
+
+    /// Stops every running power-up. Pickups still in the world stay.
+    pub fn clear(&mut self) {
+        self.ticks_left = [0; 4];
+        self.grace_ticks = 0;
+    }
+
+    pub fn reset(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed ^ POWER_UP_SEED_SALT);
+        for pickup in &mut self.pool {
+            pickup.active = false;
+        }
+        self.clear();
+        self.next_pipe = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::Obb;
+    use crate::config::GameConfig;
+    use crate::difficulty::Difficulty;
+    use crate::mode::GameMode;
+
+    const SEED: u64 = 7;
+
+    fn pipes() -> PipeManager {
+        let config = GameConfig::default();
+        let mut pipe_manager = PipeManager::new(&config, config.layout.canvas_size, SEED, GameMode::Classic);
+        pipe_manager.set_difficulty(Difficulty::base(&config));
+        pipe_manager
+    }
+
+    fn power_ups() -> PowerUps {
+        PowerUps::new(PowerUpConfig::default(), SEED)
+    }
+
+    #[test]
+    fn nothing_runs_at_first() {
+        let power_ups = power_ups();
+        assert_eq!(power_ups.running().count(), 0);
+        assert_eq!(power_ups.time_scale(), 1.0);
+        assert_eq!(power_ups.hitbox_scale(), 1.0);
+        assert_eq!(power_ups.magnet(), None);
+        assert!(!power_ups.invulnerable());
+    }
+
+    #[test]
+    fn each_kind_has_its_effect() {
+        let config = PowerUpConfig::default();
+        let mut power_ups = power_ups();
+
+        power_ups.activate(PowerUpKind::SlowMotion);
+        assert_eq!(power_ups.time_scale(), config.slow_motion_scale);
+        power_ups.activate(PowerUpKind::Shrink);
+        assert_eq!(power_ups.hitbox_scale(), config.shrink_scale);
+        power_ups.activate(PowerUpKind::Magnet);
+        assert_eq!(power_ups.magnet(), Some((config.magnet_radius, config.magnet_pull)));
+        power_ups.activate(PowerUpKind::Shield);
+
+        assert_eq!(power_ups.running().map(|(kind, _)| kind).collect::<Vec<_>>(), PowerUpKind::ALL);
+    }
+
+    #[test]
+    fn the_shield_takes_one_hit() {
+        let mut power_ups = power_ups();
+        assert!(!power_ups.break_shield());
+
+        power_ups.activate(PowerUpKind::Shield);
+        assert!(power_ups.break_shield());
+        assert!(!power_ups.is_active(PowerUpKind::Shield));
+        assert!(power_ups.invulnerable());
+        assert!(!power_ups.break_shield());
+
+        let pipe_manager = pipes();
+        let grace = (PowerUpConfig::default().shield_grace * TICK_RATE) as u64;
+        for _ in 0..grace {
+            assert!(power_ups.invulnerable());
+            power_ups.update(&pipe_manager);
+        }
+        assert!(!power_ups.invulnerable());
+    }
+
+    #[test]
+    fn stacking_is_capped() {
+        let mut power_ups = power_ups();
+        let duration = power_ups.duration_ticks();
+
+        power_ups.activate(PowerUpKind::Magnet);
+        assert_eq!(power_ups.ticks_left(PowerUpKind::Magnet), duration);
+        power_ups.activate(PowerUpKind::Magnet);
+        assert_eq!(power_ups.ticks_left(PowerUpKind::Magnet), duration * 2);
+        power_ups.activate(PowerUpKind::Magnet);
+        assert_eq!(power_ups.ticks_left(PowerUpKind::Magnet), duration * 2);
+        assert_eq!(power_ups.ticks_left(PowerUpKind::Shrink), 0);
+    }
+
+    #[test]
+    fn power_ups_run_out() {
+        let pipe_manager = pipes();
+        let mut power_ups = power_ups();
+        power_ups.activate(PowerUpKind::SlowMotion);
+        power_ups.update(&pipe_manager);
+        power_ups.activate(PowerUpKind::Shrink);
+
+        for _ in 1..power_ups.duration_ticks() {
+            power_ups.update(&pipe_manager);
+        }
+        assert_eq!(power_ups.running().collect::<Vec<_>>(), [(PowerUpKind::Shrink, 1)]);
+        assert_eq!(power_ups.time_scale(), 1.0);
+
+        power_ups.update(&pipe_manager);
+        assert_eq!(power_ups.running().count(), 0);
+    }
+
+    #[test]
+    fn pickups_start_their_power_up() {
+        let config = PowerUpConfig { chance: 1.0, ..PowerUpConfig::default() };
+        let mut pipe_manager = pipes();
+        let mut power_ups = PowerUps::new(config, SEED);
+        while pipe_manager.pipe_counter == 0 {
+            pipe_manager.update();
+            power_ups.update(&pipe_manager);
+        }
+
+        let pickup = power_ups.pool().iter().find(|pickup| pickup.active).unwrap().clone();
+        assert_eq!(power_ups.occupied_gaps().collect::<Vec<_>>(), [0]);
+        let hitbox = Obb::from(Rect::new(pickup.position.0, pickup.position.1, config.size, config.size));
+        assert_eq!(power_ups.collect(&hitbox), Some(pickup.kind));
+        assert!(power_ups.is_active(pickup.kind));
+        assert_eq!(power_ups.collect(&hitbox), None);
+        assert_eq!(power_ups.occupied_gaps().count(), 0);
+    }
+
+    #[test]
+    fn reset_clears_everything() {
+        let config = PowerUpConfig { chance: 1.0, ..PowerUpConfig::default() };
+        let mut pipe_manager = pipes();
+        let mut power_ups = PowerUps::new(config, SEED);
+        while pipe_manager.pipe_counter < 2 {
+            pipe_manager.update();
+            power_ups.update(&pipe_manager);
+        }
+        for kind in PowerUpKind::ALL {
+            power_ups.activate(kind);
+        }
+        power_ups.break_shield();
+
+        power_ups.reset(SEED);
+        assert_eq!(power_ups.running().count(), 0);
+        assert!(!power_ups.invulnerable());
+        assert_eq!(power_ups.occupied_gaps().count(), 0);
+    }
+}
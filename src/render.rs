@@ -0,0 +1,1216 @@
+use prism::Context;
+use prism::canvas::{Image, ShapeType};
+use stork::{Canvas, GameObject};
+
+use std::io::Cursor;
+
+use image::codecs::gif::GifDecoder;
+use image::imageops::FilterType;
+use image::{AnimationDecoder, Frame};
+use web_time::Instant;
+
+use crate::assets::{self, Assets};
+use crate::atlas::{Animation, Atlas};
+use crate::coin::CoinManager;
+use crate::bird::Bird;
+use crate::config::{GameConfig, LayerConfig, TiltConfig};
+use crate::collision::Rect;
+use crate::font;
+use crate::input::{Action, Binding};
+use crate::layer::ScrollingLayer;
+use crate::medal::Medal;
+use crate::mode::GameMode;
+use crate::pipe::PipeManager;
+use crate::powerup::{PowerUpKind, PowerUps};
+use crate::shooter::Shooter;
+use crate::state::GameState;
+use crate::timestep::TICK_RATE;
+
+const GROUND_DEPTH: i32 = 0;
+const LOGO_SIZE: (f32, f32) = (216.0, 170.0);
+const PANEL_SIZE: (f32, f32) = (260.0, 160.0);
+/// Ticks the game-over panel takes to slide up into place.
+const PANEL_SLIDE_TICKS: u64 = (TICK_RATE * 0.4) as u64;
+const BUTTON_SIZE: (f32, f32) = (110.0, 44.0);
+const BUTTON_GAP: f32 = 20.0;
+const BUTTON_BORDER: u32 = 3;
+const ACTION_SWATCH_SIZE: f32 = 56.0;
+const PAUSE_BAR_SIZE: (f32, f32) = (20.0, 70.0);
+/// Screen pixels per font pixel.
+const TEXT_SCALE: u32 = 2;
+const TEXT_LINE_GAP: f32 = 8.0;
+/// Most inputs listed for one action, the rest are counted as `+N`.
+const MAX_LISTED_BINDINGS: usize = 4;
+/// Widest an overlay image may be, as a fraction of the canvas width.
+const OVERLAY_MAX_WIDTH: f32 = 0.9;
+/// The bird poses are drawn at this many times the bird's size, so they
+/// stay sharp when turned.
+const POSE_SUPERSAMPLE: f32 = 2.0;
+/// Where idle pooled objects wait, well clear of any canvas.
+const PARKED_POSITION: (f32, f32) = (-10_000.0, -10_000.0);
+const BIRD_NAME: &str = "flappybird";
+
+const DIM_NAME: &str = "overlay_dim";
+const DIM_COLOR: [u8; 4] = [0, 0, 0, 120];
+/// How many steps the crash flash fades out in. Every step is a new
+/// overlay object, so it's kept coarse.
+const FLASH_STEPS: f32 = 5.0;
+const PANEL_COLOR: [u8; 4] = [222, 216, 149, 255];
+const PAUSE_BAR_COLOR: [u8; 4] = [255, 255, 255, 230];
+const BEST_COLOR: [u8; 4] = [232, 97, 1, 255];
+const BEST_UNDERLINE_HEIGHT: f32 = 4.0;
+const RESTART_COLOR: [u8; 4] = [90, 190, 80, 255];
+const MENU_COLOR: [u8; 4] = [232, 140, 40, 255];
+const SETTINGS_COLOR: [u8; 4] = [120, 130, 150, 255];
+const BUTTON_ICON_COLOR: [u8; 4] = [255, 255, 255, 255];
+const TEXT_COLOR: [u8; 4] = [255, 255, 255, 255];
+const DIM_TEXT_COLOR: [u8; 4] = [255, 255, 255, 140];
+/// One color per entry of `Medal::ALL`.
+const MEDAL_COLORS: [[u8; 4]; 4] = [
+    [205, 127, 50, 255],
+    [192, 192, 200, 255],
+    [255, 205, 60, 255],
+    [225, 240, 250, 255],
+];
+/// Where the medal goes when the score didn't earn one.
+const MEDAL_SLOT_COLOR: [u8; 4] = [190, 180, 120, 255];
+const COIN_IMAGE_SIZE: u32 = 32;
+const COIN_COLOR: [u8; 4] = [250, 205, 50, 255];
+const COIN_RIM_COLOR: [u8; 4] = [200, 130, 20, 255];
+const COIN_ICON_NAME: &str = "hud_coin_icon";
+/// One color per entry of `PowerUpKind::ALL`.
+const POWER_UP_COLORS: [[u8; 4]; 4] = [
+    [90, 160, 255, 255],
+    [180, 120, 255, 255],
+    [90, 210, 120, 255],
+    [240, 80, 80, 255],
+];
+/// One color per entry of `Action::ALL`.
+const ACTION_COLORS: [[u8; 4]; 4] = [
+    [250, 200, 40, 255],
+    [200, 200, 215, 255],
+    [90, 190, 80, 255],
+    [230, 70, 60, 255],
+];
+const MODE_SWATCH_SIZE: f32 = 28.0;
+const MODE_SWATCH_GAP: f32 = 14.0;
+/// One color per entry of `GameMode::ALL`.
+const MODE_COLORS: [[u8; 3]; 6] = [
+    [250, 200, 60],
+    [110, 200, 90],
+    [220, 70, 60],
+    [120, 170, 230],
+    [190, 110, 220],
+    [90, 220, 210],
+];
+
+/// What is drawn over the game, remembered so it can be put back after the
+/// scene is rebuilt for a new canvas size.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Overlay {
+    Title { mode: GameMode, best: usize },
+    Paused,
+    /// White over everything right after a crash, `alpha` fading to 0.
+    Flash { alpha: u8 },
+    /// `seed` is shown under the buttons, so the run can be played again.
+    GameOver { score: usize, best: usize, medal: Option<Medal>, selected: Button, seed: u64 },
+    /// The input settings, with the inputs of each action indexed like
+    /// `Action::ALL`. `rejected` is a menu key the player just tried to bind.
+    Settings { selected: Action, listening: bool, bindings: [Vec<Binding>; 4], rejected: Option<Binding> },
+}
+
+/// Something on an overlay that can be clicked or tapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Restart,
+    Menu,
+    /// Opens the input settings from the title screen.
+    Settings,
+    /// Picks the action to rebind on the settings screen.
+    Action(Action),
+}
+
+impl Button {
+    /// The game-over panel's buttons, left to right.
+    pub const PANEL: [Button; 2] = [Button::Restart, Button::Menu];
+
+    /// The panel button `steps` places along, wrapping at both ends.
+    pub fn cycle(&self, steps: i32) -> Self {
+        let len = Self::PANEL.len() as i32;
+        let index = Self::PANEL.iter().position(|button| button == self).unwrap_or(0) as i32;
+        Self::PANEL[(index + steps).rem_euclid(len) as usize]
+    }
+}
+
+/// Mirrors a `GameState` onto the stork Canvas. Owns every sprite that only
+/// matters for drawing, so the simulation itself never touches an image.
+#[derive(Debug)]
+pub struct Renderer {
+    toppipe_image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    bottompipe_image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    number_images: Vec<image::ImageBuffer<image::Rgba<u8>, Vec<u8>>>,
+    logo_image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    ground_image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    scenery_images: Vec<image::ImageBuffer<image::Rgba<u8>, Vec<u8>>>,
+    coin_image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    power_up_images: Vec<image::ImageBuffer<image::Rgba<u8>, Vec<u8>>>,
+    bullet_image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    alien_image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    /// The bird turned to every pose angle, each with all its wing frames,
+    /// ready to swap onto the bird object. Made once, a resize keeps them.
+    bird_poses: Vec<Vec<Image>>,
+    bird_fps: f32,
+    /// Wing frames played so far, counting the fractions.
+    wing_phase: f32,
+    /// When `wing_phase` was last moved on.
+    wing_clock: Instant,
+    layers: Vec<ScrollingLayer>,
+    /// Whether the bird object is on the canvas.
+    bird_spawned: bool,
+    /// Canvas names of the top and bottom object for each pipe pool slot.
+    pipe_objects: Vec<(String, String)>,
+    /// Canvas name of the object for each coin pool slot.
+    coin_objects: Vec<String>,
+    /// Canvas names of the objects for each power-up pool slot, one per
+    /// kind. Only the one for the slot's current kind is ever on screen.
+    power_up_objects: Vec<Vec<String>>,
+    bullet_objects: Vec<String>,
+    alien_objects: Vec<String>,
+    overlay: Option<Overlay>,
+    /// Every overlay object with the position it ends up at.
+    overlay_objects: Vec<(String, (f32, f32))>,
+    /// Ticks the current overlay has been showing.
+    overlay_age: u64,
+    /// Where the overlay's buttons are once it has slid in.
+    buttons: Vec<(Button, Rect)>,
+    displayed_score: Option<usize>,
+    displayed_timer: Option<usize>,
+    displayed_coins: Option<usize>,
+    /// Running power-ups and their whole seconds left, as on the HUD.
+    displayed_power_ups: Vec<(PowerUpKind, usize)>,
+    canvas_size: (f32, f32),
+    /// How far the world is thrown by the crash shake right now.
+    shake: (f32, f32),
+    config: GameConfig,
+}
+
+impl Renderer {
+    pub fn new(config: &GameConfig, assets: &Assets) -> Self {
+        let atlas = config.sprite_sheet.as_ref().and_then(|sheet| match Atlas::load(assets, sheet) {
+            Ok(atlas) => Some(atlas),
+            Err(err) => {
+                eprintln!("{}, using the separate images", err);
+                None
+            }
+        });
+        let sprite = |name: &str, file: &str| match atlas.as_ref().and_then(|atlas| atlas.sprite(name)) {
+            Some(image) => image.clone(),
+            None => assets.image(file),
+        };
+
+        let bird_animation = bird_animation(assets, atlas.as_ref());
+
+        Self {
+            toppipe_image: sprite("toppipe", "toppipe.png"),
+            bottompipe_image: sprite("bottompipe", "bottompipe.png"),
+            number_images: (0..10).map(|digit| sprite(&format!("digit_{}", digit), &format!("{}.png", digit))).collect(),
+            logo_image: assets.image("flappybird.png"),
+            ground_image: sprite("ground", "base.png"),
+            scenery_images: scenery_configs(config).map(|layer| assets.image(&layer.asset)).collect(),
+            coin_image: disc_image(COIN_COLOR, COIN_RIM_COLOR),
+            power_up_images: POWER_UP_COLORS.iter().map(|color| disc_image(*color, darker(*color))).collect(),
+            bullet_image: assets.image("bullet.png"),
+            alien_image: assets.image("alien.png"),
+            bird_poses: bird_poses(&bird_animation, config.layout.bird_size, &config.tilt),
+            bird_fps: bird_animation.fps,
+            wing_phase: 0.0,
+            wing_clock: Instant::now(),
+            layers: Vec::new(),
+            bird_spawned: false,
+            pipe_objects: Vec::new(),
+            coin_objects: Vec::new(),
+            power_up_objects: Vec::new(),
+            bullet_objects: Vec::new(),
+            alien_objects: Vec::new(),
+            overlay: None,
+            overlay_objects: Vec::new(),
+            overlay_age: 0,
+            buttons: Vec::new(),
+            displayed_score: None,
+            displayed_timer: None,
+            displayed_coins: None,
+            displayed_power_ups: Vec::new(),
+            canvas_size: config.layout.canvas_size,
+            shake: (0.0, 0.0),
+            config: config.clone(),
+        }
+    }
+
+    /// Puts the whole scene on the canvas laid out for `state.canvas_size`:
+    /// scenery back to front, the bird, the pipes, coins, the HUD and whatever
+    /// overlay is showing. Anything added before is removed first, so this is
+    /// also how the scene follows a resize.
+    pub fn build_scene(&mut self, ctx: &mut Context, canvas: &mut Canvas, state: &GameState) {
+        self.clear_scene(canvas);
+        self.canvas_size = state.canvas_size;
+
+        self.layers = self.build_layers();
+        for layer in &mut self.layers {
+            layer.spawn(ctx, canvas, self.canvas_size.0);
+        }
+
+        self.spawn_bird(ctx, canvas);
+        self.sync(ctx, canvas, state);
+
+        if let Some(overlay) = self.overlay.clone() {
+            self.add_overlay(ctx, canvas, overlay);
+        }
+    }
+
+    fn clear_scene(&mut self, canvas: &mut Canvas) {
+        for layer in &mut self.layers {
+            layer.despawn(canvas);
+        }
+
+        if std::mem::take(&mut self.bird_spawned) {
+            canvas.remove_game_object(BIRD_NAME);
+        }
+
+        for (toppipe_name, bottompipe_name) in self.pipe_objects.drain(..) {
+            canvas.remove_game_object(&toppipe_name);
+            canvas.remove_game_object(&bottompipe_name);
+        }
+
+        let pickups = self.coin_objects.drain(..).chain(self.power_up_objects.drain(..).flatten());
+        let shooter = self.bullet_objects.drain(..).chain(self.alien_objects.drain(..));
+        for name in pickups.chain(shooter) {
+            canvas.remove_game_object(&name);
+        }
+
+        remove_hud_digits(canvas, "score_digit");
+        remove_hud_digits(canvas, "timer_digit");
+        remove_hud_digits(canvas, "coin_digit");
+        canvas.remove_game_object(COIN_ICON_NAME);
+        remove_power_up_rows(canvas);
+        self.displayed_score = None;
+        self.displayed_timer = None;
+        self.displayed_coins = None;
+        self.displayed_power_ups.clear();
+        self.remove_overlay_objects(canvas);
+    }
+
+    fn build_layers(&self) -> Vec<ScrollingLayer> {
+        let layout = &self.config.layout;
+        let ground = ScrollingLayer::new(
+            "base",
+            self.ground_image.clone(),
+            (layout.base_width, layout.base_height),
+            self.canvas_size.1 - layout.base_height,
+            1.0,
+            GROUND_DEPTH,
+        ).with_tags(vec!["ground".to_string(), "obstacle".to_string()]);
+
+        let mut layers: Vec<ScrollingLayer> = scenery_configs(&self.config)
+            .zip(&self.scenery_images)
+            .enumerate()
+            .map(|(idx, (layer, image))| scenery_layer(&format!("scenery{}", idx), layer, image, self.canvas_size))
+            .chain(std::iter::once(ground))
+            .collect();
+        layers.sort_by_key(|layer| std::cmp::Reverse(layer.depth));
+        layers
+    }
+
+    /// Adds the bird object, parked until `sync` shows it. Its image is
+    /// swapped for the right pose and wing frame as it flies.
+    fn spawn_bird(&mut self, ctx: &mut Context, canvas: &mut Canvas) {
+        let hitbox_scale = self.config.physics.hitbox_scale;
+        let bird = GameObject::new(
+            ctx,
+            BIRD_NAME.to_string(),
+            self.bird_poses[0][0].clone(),
+            self.bird_side(),
+            PARKED_POSITION,
+            vec![
+                "player".to_string(),
+                "flyingbird".to_string(),
+            ],
+            (0.0, 0.0),
+            (hitbox_scale, hitbox_scale),
+            0.0,
+        );
+
+        canvas.add_game_object(BIRD_NAME.to_string(), bird);
+        self.bird_spawned = true;
+    }
+
+    /// The poses are square, wide enough for the bird at any angle.
+    fn bird_side(&self) -> f32 {
+        let size = self.config.layout.bird_size;
+        size.0.hypot(size.1)
+    }
+
+    /// Shows the pose closest to the bird's angle, centered on the bird,
+    /// with the wings moved on by the time since the last sync.
+    fn sync_bird(&mut self, canvas: &mut Canvas, bird: &Bird) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.wing_clock).as_secs_f32();
+        self.wing_clock = now;
+        let frames = self.bird_poses[0].len();
+        self.wing_phase = (self.wing_phase + elapsed * self.bird_fps * bird.wing_rate()) % frames as f32;
+
+        let tilt = &self.config.tilt;
+        let pose = ((bird.angle - tilt.max_up) / tilt.pose_step).round().clamp(0.0, (self.bird_poses.len() - 1) as f32) as usize;
+        let frame = (self.wing_phase as usize).min(frames - 1);
+
+        let side = self.bird_side();
+        let center = bird.center();
+        if let Some(obj) = canvas.get_game_object_mut(BIRD_NAME) {
+            obj.image = self.bird_poses[pose][frame].clone();
+            obj.position = (center.0 - side / 2.0, center.1 - side / 2.0);
+        }
+    }
+
+    pub fn sync(&mut self, ctx: &mut Context, canvas: &mut Canvas, state: &GameState) {
+        self.sync_bird(canvas, &state.bird);
+
+        for layer in &self.layers {
+            layer.sync(canvas, state.distance);
+        }
+
+        self.sync_pipes(ctx, canvas, &state.pipe_manager);
+        self.sync_coins(ctx, canvas, &state.coin_manager);
+        self.sync_power_ups(ctx, canvas, &state.power_ups);
+        self.sync_shooter(ctx, canvas, &state.shooter);
+        self.shake_world(canvas);
+        self.sync_score(ctx, canvas, state.score_manager.score);
+        self.sync_timer(ctx, canvas, state.time_left());
+        self.sync_coin_count(ctx, canvas, state.coin_manager.collected);
+        self.sync_power_up_timers(ctx, canvas, &state.power_ups, state.time_left().is_some());
+    }
+
+    /// Lines the pooled pipe objects up with the pool. Slots get their
+    /// objects the first time they are seen, which only happens while the
+    /// scene is being built, and idle slots are parked off screen.
+    fn sync_pipes(&mut self, ctx: &mut Context, canvas: &mut Canvas, pipe_manager: &PipeManager) {
+        for (slot, pipe) in pipe_manager.pool().iter().enumerate() {
+            if slot == self.pipe_objects.len() {
+                self.spawn_pipe_objects(ctx, canvas, slot, pipe_manager);
+            }
+
+            let (top, bottom) = if pipe.active {
+                (pipe.top_position(pipe_manager.pipe_height), pipe.bottom_position())
+            } else {
+                (PARKED_POSITION, PARKED_POSITION)
+            };
+
+            let (toppipe_name, bottompipe_name) = &self.pipe_objects[slot];
+            if let Some(obj) = canvas.get_game_object_mut(toppipe_name) {
+                obj.position = top;
+            }
+
+            if let Some(obj) = canvas.get_game_object_mut(bottompipe_name) {
+                obj.position = bottom;
+            }
+        }
+    }
+
+    fn spawn_pipe_objects(&mut self, ctx: &mut Context, canvas: &mut Canvas, slot: usize, pipe_manager: &PipeManager) {
+        let pipe_size = (pipe_manager.pipe_width, pipe_manager.pipe_height);
+        let toppipe_name = format!("toppipe_{}", slot);
+        let bottompipe_name = format!("bottompipe_{}", slot);
+
+        let toppipe_img_obj = Image {
+            shape: ShapeType::Rectangle(0.0, pipe_size, 0.0),
+            image: self.toppipe_image.clone().into(),
+            color: None
+        };
+
+        let bottompipe_img_obj = Image {
+            shape: ShapeType::Rectangle(0.0, pipe_size, 0.0),
+            image: self.bottompipe_image.clone().into(),
+            color: None
+        };
+
+        let toppipe = GameObject::new_rect(
+            ctx,
+            toppipe_name.clone(),
+            toppipe_img_obj,
+            pipe_size,
+            PARKED_POSITION,
+            vec!["pipe".to_string(), "obstacle".to_string()],
+            (0.0, 0.0),
+            (1.0, 1.0),
+            0.0,
+        );
+
+        let bottompipe = GameObject::new_rect(
+            ctx,
+            bottompipe_name.clone(),
+            bottompipe_img_obj,
+            pipe_size,
+            PARKED_POSITION,
+            vec!["pipe".to_string(), "obstacle".to_string()],
+            (0.0, 0.0),
+            (1.0, 1.0),
+            0.0,
+        );
+
+        canvas.add_game_object(toppipe_name.clone(), toppipe);
+        canvas.add_game_object(bottompipe_name.clone(), bottompipe);
+        self.pipe_objects.push((toppipe_name, bottompipe_name));
+    }
+
+    /// Same pooling as `sync_pipes`, one object per coin slot.
+    fn sync_coins(&mut self, ctx: &mut Context, canvas: &mut Canvas, coin_manager: &CoinManager) {
+        let size = coin_manager.size();
+        for (slot, coin) in coin_manager.pool().iter().enumerate() {
+            if slot == self.coin_objects.len() {
+                let name = format!("coin_{}", slot);
+                let tags = vec!["coin".to_string(), "pickup".to_string()];
+                add_sprite(ctx, canvas, &name, self.coin_image.clone(), (size, size), tags);
+                self.coin_objects.push(name);
+            }
+
+            let position = if coin.active { coin.position } else { PARKED_POSITION };
+            if let Some(obj) = canvas.get_game_object_mut(&self.coin_objects[slot]) {
+                obj.position = position;
+            }
+        }
+    }
+
+    fn sync_power_ups(&mut self, ctx: &mut Context, canvas: &mut Canvas, power_ups: &PowerUps) {
+        let size = power_ups.size();
+        for (slot, pickup) in power_ups.pool().iter().enumerate() {
+            if slot == self.power_up_objects.len() {
+                let names = PowerUpKind::ALL.iter().map(|kind| {
+                    let name = format!("powerup_{}_{}", slot, kind.index());
+                    let tags = vec!["powerup".to_string(), "pickup".to_string()];
+                    add_sprite(ctx, canvas, &name, self.power_up_images[kind.index()].clone(), (size, size), tags);
+                    name
+                }).collect();
+                self.power_up_objects.push(names);
+            }
+
+            for (kind, name) in PowerUpKind::ALL.iter().zip(&self.power_up_objects[slot]) {
+                let shown = pickup.active && pickup.kind == *kind;
+                if let Some(obj) = canvas.get_game_object_mut(name) {
+                    obj.position = if shown { pickup.position } else { PARKED_POSITION };
+                }
+            }
+        }
+    }
+
+    /// Moves everything but the HUD and the overlay by the shake. The
+    /// other syncs put every object back in place first, so it never
+    /// adds up.
+    fn shake_world(&self, canvas: &mut Canvas) {
+        if self.shake == (0.0, 0.0) {
+            return;
+        }
+
+        let names = self.layers.iter().flat_map(|layer| layer.tile_names())
+            .chain(self.bird_spawned.then(|| BIRD_NAME.to_string()))
+            .chain(self.pipe_objects.iter().flat_map(|(top, bottom)| [top.clone(), bottom.clone()]))
+            .chain(self.coin_objects.iter().cloned())
+            .chain(self.power_up_objects.iter().flatten().cloned())
+            .chain(self.bullet_objects.iter().cloned())
+            .chain(self.alien_objects.iter().cloned());
+        for name in names {
+            if let Some(obj) = canvas.get_game_object_mut(&name) {
+                obj.position = (obj.position.0 + self.shake.0, obj.position.1 + self.shake.1);
+            }
+        }
+    }
+
+    /// Plays the crash effects `ticks` after the hit: the flash fading
+    /// out and the world shaking, both dying down over their
+    /// `DeathConfig` times. Call every tick of the sequence.
+    pub fn play_death(&mut self, ctx: &mut Context, canvas: &mut Canvas, ticks: u64) {
+        let death = self.config.death;
+        let elapsed = ticks as f32 / TICK_RATE;
+        let left = |length: f32| if length > 0.0 { (1.0 - elapsed / length).max(0.0) } else { 0.0 };
+
+        let steps = (left(death.flash) * FLASH_STEPS).ceil();
+        let flash = Overlay::Flash { alpha: (steps / FLASH_STEPS * 255.0) as u8 };
+        if steps > 0.0 && self.overlay.as_ref() != Some(&flash) {
+            self.show_overlay(ctx, canvas, flash);
+        } else if steps == 0.0 && matches!(self.overlay, Some(Overlay::Flash { .. })) {
+            self.clear_overlay(canvas);
+        }
+
+        let amplitude = death.shake_amplitude * left(death.shake);
+        let phase = ticks as f32;
+        self.shake = ((phase * 2.7).sin() * amplitude, (phase * 3.1).cos() * amplitude);
+    }
+
+    /// Puts the world back still once the crash is over.
+    pub fn end_death(&mut self) {
+        self.shake = (0.0, 0.0);
+    }
+
+    /// Bullets and aliens, pooled like the pipes. Their pools grow during a
+    /// run, so new slots get their objects whenever they first show up.
+    fn sync_shooter(&mut self, ctx: &mut Context, canvas: &mut Canvas, shooter: &Shooter) {
+        let bullet_size = shooter.bullet_size();
+        for (slot, bullet) in shooter.bullets().iter().enumerate() {
+            if slot == self.bullet_objects.len() {
+                let name = format!("bullet_{}", slot);
+                let tags = vec!["bullet".to_string(), "projectile".to_string()];
+                add_sprite(ctx, canvas, &name, self.bullet_image.clone(), bullet_size, tags);
+                self.bullet_objects.push(name);
+            }
+
+            if let Some(obj) = canvas.get_game_object_mut(&self.bullet_objects[slot]) {
+                obj.position = if bullet.active { bullet.position } else { PARKED_POSITION };
+            }
+        }
+
+        let alien_size = shooter.alien_size();
+        for (slot, alien) in shooter.aliens().iter().enumerate() {
+            if slot == self.alien_objects.len() {
+                let name = format!("alien_{}", slot);
+                let tags = vec!["alien".to_string(), "enemy".to_string()];
+                add_sprite(ctx, canvas, &name, self.alien_image.clone(), alien_size, tags);
+                self.alien_objects.push(name);
+            }
+
+            if let Some(obj) = canvas.get_game_object_mut(&self.alien_objects[slot]) {
+                obj.position = if alien.active { alien.position } else { PARKED_POSITION };
+            }
+        }
+    }
+
+    fn sync_score(&mut self, ctx: &mut Context, canvas: &mut Canvas, score: usize) {
+        if self.displayed_score == Some(score) {
+            return;
+        }
+        self.displayed_score = Some(score);
+
+        let start_x = self.canvas_size.0 - self.number_width(score) - self.config.hud.margin;
+        self.place_hud_number(ctx, canvas, "score_digit", Some(score), (start_x, self.config.hud.margin));
+    }
+
+    /// Whole seconds left in a timed mode, in the top-left corner.
+    fn sync_timer(&mut self, ctx: &mut Context, canvas: &mut Canvas, ticks_left: Option<u64>) {
+        let seconds = ticks_left.map(|ticks| (ticks as f32 / TICK_RATE).ceil() as usize);
+        if self.displayed_timer == seconds {
+            return;
+        }
+        self.displayed_timer = seconds;
+
+        let margin = self.config.hud.margin;
+        self.place_hud_number(ctx, canvas, "timer_digit", seconds, (margin, margin));
+    }
+
+    /// This run's coins under the score, behind a coin icon.
+    fn sync_coin_count(&mut self, ctx: &mut Context, canvas: &mut Canvas, coins: usize) {
+        if !self.config.coins.enabled || self.displayed_coins == Some(coins) {
+            return;
+        }
+        self.displayed_coins = Some(coins);
+
+        let hud = self.config.hud;
+        let icon_size = hud.digit_size.1;
+        let y = hud.margin + hud.digit_size.1 + hud.digit_spacing;
+        let start_x = self.canvas_size.0 - self.number_width(coins) - hud.margin;
+        self.place_hud_number(ctx, canvas, "coin_digit", Some(coins), (start_x, y));
+
+        canvas.remove_game_object(COIN_ICON_NAME);
+        let img_obj = Image {
+            shape: ShapeType::Rectangle(0.0, (icon_size, icon_size), 0.0),
+            image: self.coin_image.clone().into(),
+            color: None
+        };
+        let icon = GameObject::new_rect(
+            ctx,
+            COIN_ICON_NAME.to_string(),
+            img_obj,
+            (icon_size, icon_size),
+            (start_x - hud.digit_spacing - icon_size, y),
+            vec!["score".to_string()],
+            (0.0, 0.0),
+            (1.0, 1.0),
+            0.0,
+        );
+        canvas.add_game_object(COIN_ICON_NAME.to_string(), icon);
+    }
+
+    /// A row per running power-up in the top-left corner, below the mode
+    /// timer when there is one: the power-up's icon and its seconds left.
+    fn sync_power_up_timers(&mut self, ctx: &mut Context, canvas: &mut Canvas, power_ups: &PowerUps, below_timer: bool) {
+        let rows: Vec<(PowerUpKind, usize)> = power_ups.running()
+            .map(|(kind, ticks)| (kind, (ticks as f32 / TICK_RATE).ceil() as usize))
+            .collect();
+        if rows == self.displayed_power_ups {
+            return;
+        }
+
+        remove_power_up_rows(canvas);
+
+        let hud = self.config.hud;
+        let icon_size = hud.digit_size.1;
+        let first_row = if below_timer { 1 } else { 0 };
+        for (row, (kind, seconds)) in rows.iter().enumerate() {
+            let y = hud.margin + (first_row + row) as f32 * (hud.digit_size.1 + hud.digit_spacing);
+            let name = format!("hud_powerup_icon_{}", row);
+            let img_obj = Image {
+                shape: ShapeType::Rectangle(0.0, (icon_size, icon_size), 0.0),
+                image: self.power_up_images[kind.index()].clone().into(),
+                color: None
+            };
+            let icon = GameObject::new_rect(
+                ctx,
+                name.clone(),
+                img_obj,
+                (icon_size, icon_size),
+                (hud.margin, y),
+                vec!["score".to_string()],
+                (0.0, 0.0),
+                (1.0, 1.0),
+                0.0,
+            );
+            canvas.add_game_object(name, icon);
+
+            let digits_x = hud.margin + icon_size + hud.digit_spacing;
+            self.place_hud_number(ctx, canvas, &format!("powerup_digit_{}", row), Some(*seconds), (digits_x, y));
+        }
+
+        self.displayed_power_ups = rows;
+    }
+
+    /// Replaces the digits named `{prefix}_{idx}` with `value` starting at
+    /// `start`, or just removes them for None.
+    fn place_hud_number(&self, ctx: &mut Context, canvas: &mut Canvas, prefix: &str, value: Option<usize>, start: (f32, f32)) {
+        let hud = self.config.hud;
+        let (digit_width, digit_height) = hud.digit_size;
+        let spacing = hud.digit_spacing;
+        let (start_x, start_y) = start;
+
+        remove_hud_digits(canvas, prefix);
+
+        let Some(value) = value else {
+            return;
+        };
+
+        for (idx, digit_char) in value.to_string().chars().enumerate() {
+            let digit = digit_char.to_digit(10).unwrap() as usize;
+            let digit_image = self.number_images[digit].clone();
+
+            let img_obj = Image {
+                shape: ShapeType::Rectangle(0.0, (digit_width, digit_height), 0.0),
+                image: digit_image.into(),
+                color: None
+            };
+
+            let x_pos = start_x + (idx as f32) * (digit_width + spacing);
+            let name = format!("{}_{}", prefix, idx);
+
+            let digit_obj = GameObject::new_rect(
+                ctx,
+                name.clone(),
+                img_obj,
+                (digit_width, digit_height),
+                (x_pos, start_y),
+                vec!["score".to_string()],
+                (0.0, 0.0),
+                (1.0, 1.0),
+                0.0,
+            );
+
+            canvas.add_game_object(name, digit_obj);
+        }
+    }
+
+    /// Shows `overlay`. Redrawing the overlay already up, say to move the
+    /// button selection, doesn't start its animation over.
+    pub fn show_overlay(&mut self, ctx: &mut Context, canvas: &mut Canvas, overlay: Overlay) {
+        let same_kind = self.overlay.as_ref().is_some_and(|current| std::mem::discriminant(current) == std::mem::discriminant(&overlay));
+        if !same_kind {
+            self.overlay_age = 0;
+        }
+
+        self.remove_overlay_objects(canvas);
+        self.overlay = Some(overlay.clone());
+        self.add_overlay(ctx, canvas, overlay);
+    }
+
+    pub fn clear_overlay(&mut self, canvas: &mut Canvas) {
+        self.overlay = None;
+        self.remove_overlay_objects(canvas);
+    }
+
+    fn remove_overlay_objects(&mut self, canvas: &mut Canvas) {
+        for (name, _) in self.overlay_objects.drain(..) {
+            canvas.remove_game_object(&name);
+        }
+        self.buttons.clear();
+    }
+
+    fn add_overlay(&mut self, ctx: &mut Context, canvas: &mut Canvas, overlay: Overlay) {
+        match overlay {
+            Overlay::Title { mode, best } => self.add_title(ctx, canvas, mode, best),
+            Overlay::Paused => self.add_paused(ctx, canvas),
+            Overlay::Flash { alpha } => self.add_overlay_image(ctx, canvas, "overlay_flash", solid_image([255, 255, 255, alpha]), self.canvas_size, (0.0, 0.0)),
+            Overlay::GameOver { score, best, medal, selected, seed } => {
+                let bottom = self.add_game_over(ctx, canvas, score, best, medal, selected);
+                self.add_overlay_text(ctx, canvas, "overlay_seed", &format!("seed {}", seed), TEXT_COLOR, bottom + BUTTON_GAP);
+            }
+            Overlay::Settings { selected, listening, bindings, rejected } => self.add_settings(ctx, canvas, selected, listening, &bindings, rejected),
+        }
+    }
+
+    /// Moves the overlay one tick further into place. Only the game-over
+    /// panel animates, everything above the dim slides up together.
+    pub fn advance_overlay(&mut self, canvas: &mut Canvas) {
+        if self.slide_offset() == 0.0 {
+            return;
+        }
+
+        self.overlay_age += 1;
+        let offset = self.slide_offset();
+        for (name, position) in &self.overlay_objects {
+            if let Some(obj) = canvas.get_game_object_mut(name).filter(|_| name != DIM_NAME) {
+                obj.position = (position.0, position.1 + offset);
+            }
+        }
+    }
+
+    /// How far below its resting place the overlay is drawn right now.
+    fn slide_offset(&self) -> f32 {
+        if !matches!(self.overlay, Some(Overlay::GameOver { .. })) || self.overlay_age >= PANEL_SLIDE_TICKS {
+            return 0.0;
+        }
+
+        let progress = self.overlay_age as f32 / PANEL_SLIDE_TICKS as f32;
+        let eased = 1.0 - (1.0 - progress).powi(3);
+        (1.0 - eased) * self.canvas_size.1
+    }
+
+    /// The overlay button under `point`, wherever the overlay is in its
+    /// slide.
+    pub fn button_at(&self, point: (f32, f32)) -> Option<Button> {
+        let offset = self.slide_offset();
+        let point = Rect::new(point.0, point.1 - offset, 0.0, 0.0);
+        self.buttons.iter()
+            .find(|(_, rect)| rect.x <= point.x && point.x <= rect.x + rect.w && rect.y <= point.y && point.y <= rect.y + rect.h)
+            .map(|(button, _)| *button)
+    }
+
+    /// The logo, a row of swatches for the modes with the selected one
+    /// lit up, and the selected mode's best score under it.
+    fn add_title(&mut self, ctx: &mut Context, canvas: &mut Canvas, mode: GameMode, best: usize) {
+        self.add_dim(ctx, canvas);
+
+        let size = fit_width(LOGO_SIZE, self.canvas_size.0);
+        let position = ((self.canvas_size.0 - size.0) / 2.0, self.canvas_size.1 * 0.15);
+        self.add_overlay_image(ctx, canvas, "overlay_logo", self.logo_image.clone(), size, position);
+
+        let row_width = GameMode::ALL.len() as f32 * (MODE_SWATCH_SIZE + MODE_SWATCH_GAP) - MODE_SWATCH_GAP;
+        let row_y = position.1 + size.1 + MODE_SWATCH_GAP * 2.0;
+        for (idx, color) in MODE_COLORS.iter().enumerate() {
+            let selected = idx == mode.index();
+            let swatch = if selected { MODE_SWATCH_SIZE } else { MODE_SWATCH_SIZE * 0.6 };
+            let slot_x = (self.canvas_size.0 - row_width) / 2.0 + idx as f32 * (MODE_SWATCH_SIZE + MODE_SWATCH_GAP);
+            let offset = (MODE_SWATCH_SIZE - swatch) / 2.0;
+            let name = format!("overlay_mode_{}", idx);
+            let alpha = if selected { 255 } else { 110 };
+            let image = solid_image([color[0], color[1], color[2], alpha]);
+            self.add_overlay_image(ctx, canvas, &name, image, (swatch, swatch), (slot_x + offset, row_y + offset));
+        }
+
+        let best_y = row_y + MODE_SWATCH_SIZE + MODE_SWATCH_GAP;
+        self.add_overlay_number(ctx, canvas, "overlay_best", best, self.canvas_size.0 / 2.0, best_y);
+
+        let size = BUTTON_SIZE.1;
+        let settings = Rect::new(self.canvas_size.0 - size - BUTTON_GAP, BUTTON_GAP, size, size);
+        self.add_overlay_button(ctx, canvas, "overlay_settings_button", Button::Settings, false, settings);
+    }
+
+    /// One swatch per action, the selected one drawn full size and
+    /// outlined while it waits for an input. Under the swatches every action
+    /// is listed with its inputs, then a line on what the keys do, and the
+    /// menu button that goes back to the title.
+    fn add_settings(&mut self, ctx: &mut Context, canvas: &mut Canvas, selected: Action, listening: bool, bindings: &[Vec<Binding>; 4], rejected: Option<Binding>) {
+        self.add_dim(ctx, canvas);
+
+        let row_width = Action::ALL.len() as f32 * (ACTION_SWATCH_SIZE + BUTTON_GAP) - BUTTON_GAP;
+        let row_y = self.canvas_size.1 * 0.2;
+        for (idx, action) in Action::ALL.into_iter().enumerate() {
+            let is_selected = action == selected;
+            let swatch = if is_selected { ACTION_SWATCH_SIZE } else { ACTION_SWATCH_SIZE * 0.6 };
+            let slot_x = (self.canvas_size.0 - row_width) / 2.0 + idx as f32 * (ACTION_SWATCH_SIZE + BUTTON_GAP);
+            let offset = (ACTION_SWATCH_SIZE - swatch) / 2.0;
+            let name = format!("overlay_action_{}", idx);
+            let rect = Rect::new(slot_x + offset, row_y + offset, swatch, swatch);
+            self.add_overlay_button(ctx, canvas, &name, Button::Action(action), is_selected && listening, rect);
+        }
+
+        let line_height = (font::GLYPH_SIZE.1 * TEXT_SCALE) as f32 + TEXT_LINE_GAP;
+        let mut y = row_y + ACTION_SWATCH_SIZE + BUTTON_GAP;
+        for (idx, action) in Action::ALL.into_iter().enumerate() {
+            let color = if action == selected { TEXT_COLOR } else { DIM_TEXT_COLOR };
+            let text = format!("{}: {}", action.name(), bindings_text(&bindings[idx]));
+            self.add_overlay_text(ctx, canvas, &format!("overlay_bindings_{}", idx), &text, color, y);
+            y += line_height;
+        }
+
+        let status = match rejected {
+            Some(binding) => format!("{} is kept for the menus", binding.label()),
+            None if listening => format!("press an input for {}, esc cancels", selected.name()),
+            None => "enter binds, backspace unbinds".to_string(),
+        };
+        y += TEXT_LINE_GAP;
+        self.add_overlay_text(ctx, canvas, "overlay_settings_status", &status, TEXT_COLOR, y);
+
+        let back_y = y + line_height + BUTTON_GAP;
+        let back = Rect::new((self.canvas_size.0 - BUTTON_SIZE.0) / 2.0, back_y, BUTTON_SIZE.0, BUTTON_SIZE.1);
+        self.add_overlay_button(ctx, canvas, "overlay_back_button", Button::Menu, false, back);
+    }
+
+    fn add_paused(&mut self, ctx: &mut Context, canvas: &mut Canvas) {
+        self.add_dim(ctx, canvas);
+
+        let center = (self.canvas_size.0 / 2.0, self.canvas_size.1 / 2.0);
+        let y = center.1 - PAUSE_BAR_SIZE.1 / 2.0;
+        let left = (center.0 - PAUSE_BAR_SIZE.0 * 1.5, y);
+        let right = (center.0 + PAUSE_BAR_SIZE.0 * 0.5, y);
+        self.add_overlay_image(ctx, canvas, "overlay_pause_left", solid_image(PAUSE_BAR_COLOR), PAUSE_BAR_SIZE, left);
+        self.add_overlay_image(ctx, canvas, "overlay_pause_right", solid_image(PAUSE_BAR_COLOR), PAUSE_BAR_SIZE, right);
+    }
+
+    /// The panel holds the medal on the left, and this run's score over
+    /// the all-time best on the right, the best underlined so the two
+    /// can't be mixed up. The restart and menu buttons sit below it.
+    /// Returns where the buttons end.
+    fn add_game_over(&mut self, ctx: &mut Context, canvas: &mut Canvas, score: usize, best: usize, medal: Option<Medal>, selected: Button) -> f32 {
+        self.add_dim(ctx, canvas);
+
+        let panel_size = fit_width(PANEL_SIZE, self.canvas_size.0);
+        let total_height = panel_size.1 + BUTTON_GAP + BUTTON_SIZE.1;
+        let panel_position = ((self.canvas_size.0 - panel_size.0) / 2.0, (self.canvas_size.1 - total_height) / 2.0);
+        self.add_overlay_image(ctx, canvas, "overlay_panel", solid_image(PANEL_COLOR), panel_size, panel_position);
+
+        let medal_size = panel_size.1 * 0.5;
+        let medal_position = (
+            panel_position.0 + panel_size.0 * 0.25 - medal_size / 2.0,
+            panel_position.1 + (panel_size.1 - medal_size) / 2.0,
+        );
+        let medal_color = medal.map_or(MEDAL_SLOT_COLOR, |medal| MEDAL_COLORS[medal as usize]);
+        let medal_image = disc_image(medal_color, darker(medal_color));
+        self.add_overlay_image(ctx, canvas, "overlay_medal", medal_image, (medal_size, medal_size), medal_position);
+
+        let digit_size = self.config.hud.digit_size;
+        let numbers_center = panel_position.0 + panel_size.0 * 0.65;
+        let score_y = panel_position.1 + panel_size.1 * 0.3 - digit_size.1 / 2.0;
+        let best_y = panel_position.1 + panel_size.1 * 0.68 - digit_size.1 / 2.0;
+        self.add_overlay_number(ctx, canvas, "overlay_score", score, numbers_center, score_y);
+        self.add_overlay_number(ctx, canvas, "overlay_best", best, numbers_center, best_y);
+
+        let underline_size = (self.number_width(best), BEST_UNDERLINE_HEIGHT);
+        let underline_position = (numbers_center - underline_size.0 / 2.0, best_y + digit_size.1 + BEST_UNDERLINE_HEIGHT);
+        self.add_overlay_image(ctx, canvas, "overlay_best_underline", solid_image(BEST_COLOR), underline_size, underline_position);
+
+        let row_width = BUTTON_SIZE.0 * 2.0 + BUTTON_GAP;
+        let row_y = panel_position.1 + panel_size.1 + BUTTON_GAP;
+        for (idx, button) in Button::PANEL.into_iter().enumerate() {
+            let x = (self.canvas_size.0 - row_width) / 2.0 + idx as f32 * (BUTTON_SIZE.0 + BUTTON_GAP);
+            let name = format!("overlay_button_{}", idx);
+            self.add_overlay_button(ctx, canvas, &name, button, button == selected, Rect::new(x, row_y, BUTTON_SIZE.0, BUTTON_SIZE.1));
+        }
+
+        row_y + BUTTON_SIZE.1
+    }
+
+    fn add_overlay_button(&mut self, ctx: &mut Context, canvas: &mut Canvas, name: &str, button: Button, selected: bool, rect: Rect) {
+        let size = (rect.w, rect.h);
+        self.add_overlay_image(ctx, canvas, name, button_image(button, selected, size), size, (rect.x, rect.y));
+        self.buttons.push((button, rect));
+    }
+
+    fn add_overlay_number(&mut self, ctx: &mut Context, canvas: &mut Canvas, prefix: &str, value: usize, center_x: f32, y: f32) {
+        let hud = self.config.hud;
+        let start_x = center_x - self.number_width(value) / 2.0;
+        for (idx, digit_char) in value.to_string().chars().enumerate() {
+            let digit = digit_char.to_digit(10).unwrap() as usize;
+            let x = start_x + (idx as f32) * (hud.digit_size.0 + hud.digit_spacing);
+            let name = format!("{}_{}", prefix, idx);
+            self.add_overlay_image(ctx, canvas, &name, self.number_images[digit].clone(), hud.digit_size, (x, y));
+        }
+    }
+
+    /// One line of text centered across the canvas, shrunk to fit if it
+    /// is too wide.
+    fn add_overlay_text(&mut self, ctx: &mut Context, canvas: &mut Canvas, name: &str, text: &str, color: [u8; 4], y: f32) {
+        let image = font::text_image(text, TEXT_SCALE, color);
+        let size = fit_width((image.width() as f32, image.height() as f32), self.canvas_size.0);
+        self.add_overlay_image(ctx, canvas, name, image, size, ((self.canvas_size.0 - size.0) / 2.0, y));
+    }
+
+    fn number_width(&self, value: usize) -> f32 {
+        let digits = value.to_string().len() as f32;
+        let hud = &self.config.hud;
+        digits * (hud.digit_size.0 + hud.digit_spacing) - hud.digit_spacing
+    }
+
+    fn add_dim(&mut self, ctx: &mut Context, canvas: &mut Canvas) {
+        self.add_overlay_image(ctx, canvas, DIM_NAME, solid_image(DIM_COLOR), self.canvas_size, (0.0, 0.0));
+    }
+
+    fn add_overlay_image(
+        &mut self,
+        ctx: &mut Context,
+        canvas: &mut Canvas,
+        name: &str,
+        image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+        size: (f32, f32),
+        position: (f32, f32),
+    ) {
+        let img_obj = Image {
+            shape: ShapeType::Rectangle(0.0, size, 0.0),
+            image: image.into(),
+            color: None
+        };
+
+        let offset = if name == DIM_NAME { 0.0 } else { self.slide_offset() };
+        let obj = GameObject::new_rect(
+            ctx,
+            name.to_string(),
+            img_obj,
+            size,
+            (position.0, position.1 + offset),
+            vec!["overlay".to_string()],
+            (0.0, 0.0),
+            (1.0, 1.0),
+            0.0,
+        );
+
+        canvas.add_game_object(name.to_string(), obj);
+        self.overlay_objects.push((name.to_string(), position));
+    }
+}
+
+fn remove_hud_digits(canvas: &mut Canvas, prefix: &str) {
+    for idx in 0..10 {
+        canvas.remove_game_object(&format!("{}_{}", prefix, idx));
+    }
+}
+
+/// Adds a parked sprite for a pooled world object.
+fn add_sprite(
+    ctx: &mut Context,
+    canvas: &mut Canvas,
+    name: &str,
+    image: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    size: (f32, f32),
+    tags: Vec<String>,
+) {
+    let img_obj = Image {
+        shape: ShapeType::Rectangle(0.0, size, 0.0),
+        image: image.into(),
+        color: None
+    };
+
+    let obj = GameObject::new_rect(
+        ctx,
+        name.to_string(),
+        img_obj,
+        size,
+        PARKED_POSITION,
+        tags,
+        (0.0, 0.0),
+        (1.0, 1.0),
+        0.0,
+    );
+
+    canvas.add_game_object(name.to_string(), obj);
+}
+
+fn remove_power_up_rows(canvas: &mut Canvas) {
+    for row in 0..PowerUpKind::ALL.len() {
+        canvas.remove_game_object(&format!("hud_powerup_icon_{}", row));
+        remove_hud_digits(canvas, &format!("powerup_digit_{}", row));
+    }
+}
+
+fn solid_image(rgba: [u8; 4]) -> image::ImageBuffer<image::Rgba<u8>, Vec<u8>> {
+    image::ImageBuffer::from_pixel(4, 4, image::Rgba(rgba))
+}
+
+/// A disc with a rim, for the pickups that have no sprite among the assets.
+fn disc_image(color: [u8; 4], rim_color: [u8; 4]) -> image::ImageBuffer<image::Rgba<u8>, Vec<u8>> {
+    let radius = COIN_IMAGE_SIZE as f32 / 2.0;
+    image::ImageBuffer::from_fn(COIN_IMAGE_SIZE, COIN_IMAGE_SIZE, |x, y| {
+        let dx = x as f32 + 0.5 - radius;
+        let dy = y as f32 + 0.5 - radius;
+        let distance = (dx * dx + dy * dy).sqrt();
+        if distance > radius {
+            image::Rgba([0, 0, 0, 0])
+        } else if distance > radius * 0.75 {
+            image::Rgba(rim_color)
+        } else {
+            image::Rgba(color)
+        }
+    })
+}
+
+/// A colored button with a white icon, a play triangle for restart, three
+/// bars for the menu and a cog for the settings. Action swatches are plain.
+/// The selected one gets a white border.
+fn button_image(button: Button, selected: bool, size: (f32, f32)) -> image::ImageBuffer<image::Rgba<u8>, Vec<u8>> {
+    let (width, height) = (size.0.max(1.0) as u32, size.1.max(1.0) as u32);
+    let color = match button {
+        Button::Restart => RESTART_COLOR,
+        Button::Menu => MENU_COLOR,
+        Button::Settings => SETTINGS_COLOR,
+        Button::Action(action) => ACTION_COLORS[action.index()],
+    };
+    let border = if selected { BUTTON_ICON_COLOR } else { darker(color) };
+
+    // The icon fits a square in the middle, half the button's height.
+    let icon = height as f32 / 2.0;
+    let left = (width as f32 - icon) / 2.0;
+    let top = (height as f32 - icon) / 2.0;
+
+    image::ImageBuffer::from_fn(width, height, |x, y| {
+        if x < BUTTON_BORDER || y < BUTTON_BORDER || x + BUTTON_BORDER >= width || y + BUTTON_BORDER >= height {
+            return image::Rgba(border);
+        }
+
+        let u = (x as f32 + 0.5 - left) / icon;
+        let v = (y as f32 + 0.5 - top) / icon;
+        let inside = (0.0..1.0).contains(&u) && (0.0..1.0).contains(&v);
+        let on_icon = inside && match button {
+            Button::Restart => (v - 0.5).abs() <= u * 0.5,
+            Button::Menu => matches!((v * 5.0) as u32, 0 | 2 | 4),
+            Button::Settings => {
+                let (du, dv) = (u - 0.5, v - 0.5);
+                let radius = (du * du + dv * dv).sqrt();
+                let tooth = (dv.atan2(du) / std::f32::consts::TAU * 8.0).rem_euclid(1.0) < 0.5;
+                radius > 0.15 && (radius < 0.36 || (tooth && radius < 0.5))
+            }
+            Button::Action(_) => false,
+        };
+
+        image::Rgba(if on_icon { BUTTON_ICON_COLOR } else { color })
+    })
+}
+
+fn darker(color: [u8; 4]) -> [u8; 4] {
+    [color[0] / 2, color[1] / 2, color[2] / 2, color[3]]
+}
+
+/// The inputs of one action for the settings screen.
+fn bindings_text(bindings: &[Binding]) -> String {
+    if bindings.is_empty() {
+        return "nothing".to_string();
+    }
+    let mut labels = bindings.iter().take(MAX_LISTED_BINDINGS).map(Binding::label).collect::<Vec<_>>();
+    if bindings.len() > MAX_LISTED_BINDINGS {
+        labels.push(format!("+{}", bindings.len() - MAX_LISTED_BINDINGS));
+    }
+    labels.join(" ")
+}
+
+/// Shrinks `size` to fit a canvas narrower than it, keeping the aspect
+/// ratio and a small margin on both sides.
+fn fit_width(size: (f32, f32), canvas_width: f32) -> (f32, f32) {
+    let max_width = canvas_width * OVERLAY_MAX_WIDTH;
+    if size.0 <= max_width {
+        return size;
+    }
+    (max_width, size.1 * max_width / size.0)
+}
+
+fn scenery_configs(config: &GameConfig) -> impl Iterator<Item = &LayerConfig> {
+    std::iter::once(&config.scenery.background).chain(&config.scenery.clouds)
+}
+
+fn scenery_layer(
+    name: &str,
+    config: &LayerConfig,
+    image: &image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    canvas_size: (f32, f32),
+) -> ScrollingLayer {
+    let height = config.height.unwrap_or(canvas_size.1);
+    let width = height * image.width() as f32 / image.height() as f32;
+
+    ScrollingLayer::new(name, image.clone(), (width, height), config.y, config.speed, config.depth)
+}
+
+/// The bird's frames: the sheet's `bird` animation or sprite when there is
+/// one, otherwise the override GIF, then the embedded GIF, and a
+/// placeholder if neither decodes.
+fn bird_animation(assets: &Assets, atlas: Option<&Atlas>) -> Animation {
+    const FPS: f32 = 12.0;
+
+    if let Some(animation) = atlas.and_then(|atlas| atlas.animation("bird")) {
+        return animation.clone();
+    }
+    if let Some(sprite) = atlas.and_then(|atlas| atlas.sprite("bird")) {
+        return Animation { frames: vec![sprite.clone()], fps: FPS };
+    }
+
+    match assets.bytes("flappybird.gif").map(|bytes| gif_frames(&bytes)) {
+        Ok(Ok(frames)) => return Animation { frames, fps: FPS },
+        Ok(Err(err)) => eprintln!("Could not decode flappybird.gif: {}", err),
+        Err(err) => eprintln!("{}", err),
+    }
+
+    let embedded = Assets::embedded_bytes("flappybird.gif").map_err(|err| err.to_string());
+    match embedded.and_then(|bytes| gif_frames(bytes).map_err(|err| err.to_string())) {
+        Ok(frames) => Animation { frames, fps: FPS },
+        Err(err) => {
+            eprintln!("Could not decode the built-in flappybird.gif, the bird won't animate: {}", err);
+            Animation { frames: vec![assets::placeholder()], fps: FPS }
+        }
+    }
+}
+
+fn gif_frames(bytes: &[u8]) -> Result<Vec<image::RgbaImage>, image::ImageError> {
+    let frames = GifDecoder::new(Cursor::new(bytes))?.into_frames().collect_frames()?;
+    Ok(frames.into_iter().map(Frame::into_buffer).collect())
+}
+
+/// Pre-rotates every frame of the bird to every `tilt.pose_step` between
+/// `tilt.max_up` and `tilt.max_down`. Pose `idx` is turned
+/// `max_up + idx * pose_step` degrees.
+fn bird_poses(animation: &Animation, size: (f32, f32), tilt: &TiltConfig) -> Vec<Vec<Image>> {
+    let width = (size.0 * POSE_SUPERSAMPLE).round().max(1.0) as u32;
+    let height = (size.1 * POSE_SUPERSAMPLE).round().max(1.0) as u32;
+    let side = (width as f32).hypot(height as f32).ceil() as u32;
+    let shown_side = size.0.hypot(size.1);
+    let frames = animation.frames.iter()
+        .map(|frame| image::imageops::resize(frame, width, height, FilterType::Nearest))
+        .collect::<Vec<_>>();
+
+    (0..tilt.pose_count() as usize)
+        .map(|idx| {
+            let angle = (tilt.max_up + idx as f32 * tilt.pose_step).min(tilt.max_down);
+            frames.iter()
+                .map(|frame| Image {
+                    shape: ShapeType::Rectangle(0.0, (shown_side, shown_side), 0.0),
+                    image: rotated(frame, angle, side).into(),
+                    color: None
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// `image` turned `degrees` clockwise around its center, on a transparent
+/// `side` by `side` square big enough for any angle.
+fn rotated(image: &image::RgbaImage, degrees: f32, side: u32) -> image::RgbaImage {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let center = side as f32 / 2.0;
+    let (half_width, half_height) = (image.width() as f32 / 2.0, image.height() as f32 / 2.0);
+
+    image::ImageBuffer::from_fn(side, side, |x, y| {
+        let (dx, dy) = (x as f32 + 0.5 - center, y as f32 + 0.5 - center);
+        let source_x = dx * cos + dy * sin + half_width;
+        let source_y = -dx * sin + dy * cos + half_height;
+        if source_x < 0.0 || source_y < 0.0 || source_x >= image.width() as f32 || source_y >= image.height() as f32 {
+            return image::Rgba([0, 0, 0, 0]);
+        }
+        *image.get_pixel(source_x as u32, source_y as u32)
+    })
+}
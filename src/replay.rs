@@ -0,0 +1,307 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::mode::GameMode;
+use crate::savefile;
+
+pub const REPLAY_VERSION: u32 = 5;
+/// Oldest version `Replay::from_text` still reads. Version 1 files simply
+/// have no resize entries, files before version 3 are all classic mode,
+/// files before version 4 never fire and files before version 5 have no end.
+const OLDEST_REPLAY_VERSION: u32 = 1;
+const REPLAY_MAGIC: &str = "rampy-replay";
+
+/// Everything needed to rerun a game tick for tick: the pipe seed, the mode,
+/// the canvas size the run started at, every resize after that, the tick
+/// of every flap and shot, and the tick the run ended on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub canvas_size: (f32, f32),
+    pub resizes: Vec<(u64, (f32, f32))>,
+    pub flaps: Vec<u64>,
+    pub fires: Vec<u64>,
+    /// Set once the run is over, however it ended. Runs that were quit
+    /// from the pause screen only stop here.
+    pub end: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    UnsupportedVersion(u32),
+    Malformed { line: usize, message: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "could not access replay file: {}", err),
+            ReplayError::UnsupportedVersion(version) => write!(f, "replay version {} is not supported (expected {} to {})", version, OLDEST_REPLAY_VERSION, REPLAY_VERSION),
+            ReplayError::Malformed { line, message } => write!(f, "replay line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl Replay {
+    pub fn new(seed: u64, canvas_size: (f32, f32), mode: GameMode) -> Self {
+        Self {
+            seed,
+            mode,
+            canvas_size,
+            resizes: Vec::new(),
+            flaps: Vec::new(),
+            fires: Vec::new(),
+            end: None,
+        }
+    }
+
+    pub fn record_flap(&mut self, tick: u64) {
+        self.flaps.push(tick);
+    }
+
+    pub fn record_fire(&mut self, tick: u64) {
+        self.fires.push(tick);
+    }
+
+    pub fn record_resize(&mut self, tick: u64, canvas_size: (f32, f32)) {
+        self.resizes.push((tick, canvas_size));
+    }
+
+    pub fn record_end(&mut self, tick: u64) {
+        self.end = Some(tick);
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        savefile::write_text(path, &self.to_text())?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        Self::from_text(&fs::read_to_string(path)?)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} {}\n", REPLAY_MAGIC, REPLAY_VERSION);
+        text.push_str(&format!("seed {}\n", self.seed));
+        text.push_str(&format!("mode {}\n", self.mode.name()));
+        text.push_str(&format!("size {} {}\n", self.canvas_size.0, self.canvas_size.1));
+        for (tick, size) in &self.resizes {
+            text.push_str(&format!("resize {} {} {}\n", tick, size.0, size.1));
+        }
+        for tick in &self.flaps {
+            text.push_str(&format!("flap {}\n", tick));
+        }
+        for tick in &self.fires {
+            text.push_str(&format!("fire {}\n", tick));
+        }
+        if let Some(tick) = self.end {
+            text.push_str(&format!("end {}\n", tick));
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, ReplayError> {
+        let mut lines = text.lines().enumerate().map(|(idx, line)| (idx + 1, line.trim()));
+
+        let malformed = |line: usize, message: &str| ReplayError::Malformed { line, message: message.to_string() };
+
+        match lines.next() {
+            Some((_, header)) if header.starts_with(REPLAY_MAGIC) => {
+                let version = header[REPLAY_MAGIC.len()..].trim().parse::<u32>()
+                    .map_err(|_| malformed(1, "missing replay version"))?;
+                if !(OLDEST_REPLAY_VERSION..=REPLAY_VERSION).contains(&version) {
+                    return Err(ReplayError::UnsupportedVersion(version));
+                }
+            }
+            _ => return Err(malformed(1, "not a replay file")),
+        }
+
+        let mut seed = None;
+        let mut mode = GameMode::Classic;
+        let mut canvas_size = None;
+        let mut resizes: Vec<(u64, (f32, f32))> = Vec::new();
+        let mut flaps = Vec::new();
+        let mut fires = Vec::new();
+        let mut end = None;
+
+        for (line, content) in lines {
+            let mut fields = content.split_whitespace();
+            match (fields.next(), fields.next(), fields.next()) {
+                (None, _, _) => continue,
+                (Some("resize"), Some(tick), Some(width)) => {
+                    let tick = tick.parse().map_err(|_| malformed(line, "invalid resize tick"))?;
+                    let width = width.parse().map_err(|_| malformed(line, "invalid width"))?;
+                    let height = fields.next().and_then(|height| height.parse().ok())
+                        .ok_or_else(|| malformed(line, "invalid height"))?;
+                    if resizes.last().is_some_and(|(last, _)| *last > tick) {
+                        return Err(malformed(line, "resize ticks must be in order"));
+                    }
+                    resizes.push((tick, (width, height)));
+                }
+                (Some("mode"), Some(name), None) => {
+                    mode = GameMode::from_name(name).ok_or_else(|| malformed(line, &format!("unknown mode '{}'", name)))?;
+                }
+                (Some("seed"), Some(value), None) => {
+                    seed = Some(value.parse().map_err(|_| malformed(line, "invalid seed"))?);
+                }
+                (Some("size"), Some(width), Some(height)) => {
+                    let width = width.parse().map_err(|_| malformed(line, "invalid width"))?;
+                    let height = height.parse().map_err(|_| malformed(line, "invalid height"))?;
+                    canvas_size = Some((width, height));
+                }
+                (Some("flap"), Some(value), None) => {
+                    let tick = value.parse().map_err(|_| malformed(line, "invalid flap tick"))?;
+                    if flaps.last().is_some_and(|last| *last > tick) {
+                        return Err(malformed(line, "flap ticks must be in order"));
+                    }
+                    flaps.push(tick);
+                }
+                (Some("fire"), Some(value), None) => {
+                    let tick = value.parse().map_err(|_| malformed(line, "invalid fire tick"))?;
+                    if fires.last().is_some_and(|last| *last > tick) {
+                        return Err(malformed(line, "fire ticks must be in order"));
+                    }
+                    fires.push(tick);
+                }
+                (Some("end"), Some(value), None) => {
+                    end = Some(value.parse().map_err(|_| malformed(line, "invalid end tick"))?);
+                }
+                (Some(other), _, _) => return Err(malformed(line, &format!("unknown entry '{}'", other))),
+            }
+        }
+
+        Ok(Self {
+            seed: seed.ok_or_else(|| malformed(0, "missing seed"))?,
+            mode,
+            canvas_size: canvas_size.ok_or_else(|| malformed(0, "missing size"))?,
+            resizes,
+            flaps,
+            fires,
+            end,
+        })
+    }
+}
+
+/// Feeds a recorded run back into the simulation one tick at a time.
+#[derive(Debug)]
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    cursor: usize,
+    fire_cursor: usize,
+    resize_cursor: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, cursor: 0, fire_cursor: 0, resize_cursor: 0 }
+    }
+
+    /// The canvas size the recorded run switched to at or before `tick`, if
+    /// it hasn't been handed out yet. Only the latest one matters.
+    pub fn resize_at(&mut self, tick: u64) -> Option<(f32, f32)> {
+        let mut size = None;
+        while let Some(&(recorded, recorded_size)) = self.replay.resizes.get(self.resize_cursor) {
+            if recorded > tick {
+                break;
+            }
+            size = Some(recorded_size);
+            self.resize_cursor += 1;
+        }
+        size
+    }
+
+    /// Whether the recorded run flapped on `tick`. Ticks must be asked for
+    /// in increasing order, the same way the simulation steps through them.
+    pub fn flap_at(&mut self, tick: u64) -> bool {
+        take_at(&self.replay.flaps, &mut self.cursor, tick)
+    }
+
+    /// Like `flap_at`, for shots.
+    pub fn fire_at(&mut self, tick: u64) -> bool {
+        take_at(&self.replay.fires, &mut self.fire_cursor, tick)
+    }
+
+    /// Whether the recorded run was over by `tick`.
+    pub fn ended_at(&self, tick: u64) -> bool {
+        self.replay.end.is_some_and(|end| tick >= end)
+    }
+}
+
+/// Whether `ticks` holds `tick`, moving `cursor` past it and anything
+/// before it.
+fn take_at(ticks: &[u64], cursor: &mut usize, tick: u64) -> bool {
+    let mut found = false;
+    while let Some(&recorded) = ticks.get(*cursor) {
+        if recorded > tick {
+            break;
+        }
+        found |= recorded == tick;
+        *cursor += 1;
+    }
+    found
+}
+
+/// Where flaps come from for the current run.
+#[derive(Debug)]
+pub enum ReplayMode {
+    Recording(Replay),
+    Playback(ReplayPlayer),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_text() {
+        let mut replay = Replay::new(42, (800.0, 600.0), GameMode::Zen);
+        replay.record_flap(3);
+        replay.record_resize(10, (400.0, 700.0));
+        replay.record_flap(12);
+        replay.record_fire(15);
+        replay.record_end(90);
+
+        assert_eq!(Replay::from_text(&replay.to_text()).unwrap(), replay);
+    }
+
+    #[test]
+    fn reads_older_versions_without_an_end() {
+        let replay = Replay::from_text("rampy-replay 4\nseed 1\nmode classic\nsize 800 600\nflap 5\n").unwrap();
+        assert_eq!(replay.flaps, vec![5]);
+        assert_eq!(replay.end, None);
+    }
+
+    #[test]
+    fn playback_stops_at_the_end() {
+        let mut replay = Replay::new(1, (800.0, 600.0), GameMode::Zen);
+        replay.record_end(20);
+        let player = ReplayPlayer::new(replay);
+
+        assert!(!player.ended_at(19));
+        assert!(player.ended_at(20));
+    }
+
+    #[test]
+    fn save_replaces_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("last_run.replay");
+        let mut replay = Replay::new(5, (800.0, 600.0), GameMode::Classic);
+        replay.save(&path).unwrap();
+        replay.record_end(7);
+        replay.save(&path).unwrap();
+
+        assert_eq!(Replay::load(&path).unwrap(), replay);
+    }
+}
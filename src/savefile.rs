@@ -0,0 +1,173 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "could not access save file: {}", err),
+            SaveError::Parse(err) => write!(f, "could not parse save file: {}", err),
+            SaveError::Serialize(err) => write!(f, "could not write save file: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for SaveError {
+    fn from(err: toml::de::Error) -> Self {
+        SaveError::Parse(err)
+    }
+}
+
+impl From<toml::ser::Error> for SaveError {
+    fn from(err: toml::ser::Error) -> Self {
+        SaveError::Serialize(err)
+    }
+}
+
+pub fn read<T: DeserializeOwned>(path: &Path) -> Result<T, SaveError> {
+    Ok(toml::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Reads a save file, falling back to the backup left by the last
+/// successful `write` when the file is unreadable, and to the default when
+/// neither can be used. A missing file is not worth reporting.
+pub fn load<T: DeserializeOwned + Default>(path: &Path) -> T {
+    let err = match read(path) {
+        Ok(value) => return value,
+        Err(SaveError::Io(err)) if err.kind() == io::ErrorKind::NotFound => return T::default(),
+        Err(err) => err,
+    };
+
+    let backup = backup_path(path);
+    match read(&backup) {
+        Ok(value) => {
+            eprintln!("{} ({}), restored the backup from {}", err, path.display(), backup.display());
+            value
+        }
+        Err(_) => {
+            eprintln!("{} ({}), starting over", err, path.display());
+            T::default()
+        }
+    }
+}
+
+/// Saves `value` with `write_text`. The previous save is kept as a backup
+/// first, as long as it still parses.
+pub fn write<T: Serialize + DeserializeOwned>(path: &Path, value: &T) -> Result<(), SaveError> {
+    let text = toml::to_string(value)?;
+    if read::<T>(path).is_ok() {
+        fs::copy(path, backup_path(path))?;
+    }
+    write_text(path, &text)?;
+    Ok(())
+}
+
+/// Writes to a temporary file and renames it over `path`, so a crash
+/// mid-write never leaves a half-written file behind.
+pub fn write_text(path: &Path, text: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let tmp = sibling(path, "tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(text.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    sibling(path, "bak")
+}
+
+/// `path` with `suffix` added after its extension, e.g. `scores.toml.bak`.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Save {
+        value: u32,
+    }
+
+    #[test]
+    fn write_then_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("save.toml");
+
+        write(&path, &Save { value: 3 }).unwrap();
+        assert_eq!(read::<Save>(&path).unwrap(), Save { value: 3 });
+        assert_eq!(load::<Save>(&path), Save { value: 3 });
+        assert!(!sibling(&path, "tmp").exists());
+    }
+
+    #[test]
+    fn missing_file_loads_the_default() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(load::<Save>(&dir.path().join("save.toml")), Save::default());
+    }
+
+    #[test]
+    fn corrupt_file_falls_back_to_the_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("save.toml");
+
+        write(&path, &Save { value: 1 }).unwrap();
+        write(&path, &Save { value: 2 }).unwrap();
+        assert_eq!(read::<Save>(&backup_path(&path)).unwrap(), Save { value: 1 });
+
+        fs::write(&path, "value = ").unwrap();
+        assert_eq!(load::<Save>(&path), Save { value: 1 });
+    }
+
+    #[test]
+    fn corrupt_file_is_not_backed_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("save.toml");
+
+        write(&path, &Save { value: 1 }).unwrap();
+        write(&path, &Save { value: 2 }).unwrap();
+        fs::write(&path, "not toml").unwrap();
+        write(&path, &Save { value: 3 }).unwrap();
+
+        assert_eq!(read::<Save>(&backup_path(&path)).unwrap(), Save { value: 1 });
+        assert_eq!(load::<Save>(&path), Save { value: 3 });
+    }
+
+    #[test]
+    fn corrupt_file_without_a_backup_loads_the_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("save.toml");
+
+        fs::write(&path, "value = \"three\"").unwrap();
+        assert_eq!(load::<Save>(&path), Save::default());
+    }
+}
@@ -0,0 +1,19 @@
+use crate::timestep::TICK_RATE;
+
+/// Grace period on the game-over panel so a panicked flap doesn't restart.
+pub const GAME_OVER_INPUT_DELAY: u64 = (TICK_RATE * 0.5) as u64;
+
+/// The screen `Game` is currently showing. Only `Playing` advances pipes,
+/// ground and score; the others drive the bird or wait for input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    Title,
+    GetReady,
+    Playing,
+    /// The crash plays out, see `DeathConfig`. Input is ignored.
+    Dying,
+    GameOver,
+    Paused,
+    /// Rebinding the controls, reached from the title.
+    Settings,
+}
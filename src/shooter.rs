@@ -0,0 +1,338 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::collision::{Obb, Rect};
+use crate::config::ShooterConfig;
+use crate::pipe::PipeManager;
+use crate::timestep::TICK_RATE;
+
+/// Same idea as the coin salt, a random sequence of their own for a seed.
+const ALIEN_SEED_SALT: u64 = 0x616c_6965_6e73_5f21;
+
+/// One slot of the bullet pool. `position` is the top-left corner.
+#[derive(Debug, Clone)]
+pub struct Bullet {
+    pub position: (f32, f32),
+    pub active: bool,
+}
+
+/// One slot of the alien pool. `position` is the top-left corner.
+#[derive(Debug, Clone)]
+pub struct Alien {
+    pub position: (f32, f32),
+    /// Height the alien bobs around.
+    pub anchor_y: f32,
+    /// Ticks since it spawned.
+    pub age: u32,
+    pub active: bool,
+}
+
+/// Bullets fired by the bird and the aliens flying in between the pipes,
+/// for shooter mode. Both live in pools that grow as needed and never
+/// shrink, like the pipes.
+///
+/// Bullet and alien hits are worked out here rather than with
+/// `Canvas::collision_between`. The canvas only sees objects where the last
+/// frame drew them, and frames don't line up with ticks. Checking against
+/// the sim's own positions lets a replay hit the same aliens on the same ticks.
+#[derive(Debug)]
+pub struct Shooter {
+    bullets: Vec<Bullet>,
+    aliens: Vec<Alien>,
+    /// Ticks until the bird can fire again.
+    cooldown: u64,
+    /// Id of the first pipe pair that hasn't been offered an alien yet.
+    next_pipe: u32,
+    enabled: bool,
+    rng: StdRng,
+    config: ShooterConfig,
+}
+
+impl Shooter {
+    pub fn new(config: ShooterConfig, seed: u64, enabled: bool) -> Self {
+        Self {
+            bullets: Vec::new(),
+            aliens: Vec::new(),
+            cooldown: 0,
+            next_pipe: 0,
+            enabled,
+            rng: StdRng::seed_from_u64(seed ^ ALIEN_SEED_SALT),
+            config,
+        }
+    }
+
+    pub fn bullets(&self) -> &[Bullet] {
+        &self.bullets
+    }
+
+    pub fn aliens(&self) -> &[Alien] {
+        &self.aliens
+    }
+
+    pub fn bullet_size(&self) -> (f32, f32) {
+        self.config.bullet_size
+    }
+
+    pub fn alien_size(&self) -> (f32, f32) {
+        self.config.alien_size
+    }
+
+    /// Fires from `muzzle`, the point the bullet's left edge is centered
+    /// on, unless the last shot is still cooling down.
+    pub fn fire(&mut self, muzzle: (f32, f32)) -> bool {
+        if !self.enabled || self.cooldown > 0 {
+            return false;
+        }
+        self.cooldown = (self.config.fire_cooldown * TICK_RATE) as u64;
+
+        let position = (muzzle.0, muzzle.1 - self.config.bullet_size.1 / 2.0);
+        match self.bullets.iter_mut().find(|bullet| !bullet.active) {
+            Some(bullet) => *bullet = Bullet { position, active: true },
+            None => self.bullets.push(Bullet { position, active: true }),
+        }
+        true
+    }
+
+    /// Moves bullets and aliens, spawns aliens between new pipe pairs and
+    /// resolves bullets against pipes and aliens. Call after
+    /// `PipeManager::update`. `time_scale` slows the aliens with the pipes.
+    pub fn update(&mut self, pipe_manager: &PipeManager, canvas_size: (f32, f32), ground_y: f32, time_scale: f32) -> usize {
+        self.cooldown = self.cooldown.saturating_sub(1);
+
+        while self.next_pipe < pipe_manager.pipe_counter {
+            self.offer_alien(pipe_manager, self.next_pipe, ground_y);
+            self.next_pipe += 1;
+        }
+
+        for bullet in self.bullets.iter_mut().filter(|bullet| bullet.active) {
+            bullet.position.0 += self.config.bullet_speed;
+            let rect = Rect::new(bullet.position.0, bullet.position.1, self.config.bullet_size.0, self.config.bullet_size.1);
+            if bullet.position.0 > canvas_size.0 || pipe_manager.collides_with(&Obb::from(rect)) {
+                bullet.active = false;
+            }
+        }
+
+        let alien_size = self.config.alien_size;
+        let max_y = (ground_y - alien_size.1).max(0.0);
+        let speed = pipe_manager.speed() + self.config.alien_speed * time_scale;
+        for alien in self.aliens.iter_mut().filter(|alien| alien.active) {
+            alien.age += 1;
+            alien.position.0 += speed;
+            let bob = (alien.age as f32 * self.config.alien_bob_speed).sin() * self.config.alien_bob_amplitude;
+            alien.position.1 = (alien.anchor_y + bob).clamp(0.0, max_y);
+            if alien.position.0 < -alien_size.0 {
+                alien.active = false;
+            }
+        }
+
+        self.resolve_hits()
+    }
+
+    /// Removes every bullet and alien that touch. Returns how many aliens
+    /// went down.
+    fn resolve_hits(&mut self) -> usize {
+        let bullet_size = self.config.bullet_size;
+        let alien_size = self.config.alien_size;
+        let mut destroyed = 0;
+
+        for bullet in self.bullets.iter_mut().filter(|bullet| bullet.active) {
+            let bullet_rect = Rect::new(bullet.position.0, bullet.position.1, bullet_size.0, bullet_size.1);
+            let hit = self.aliens.iter_mut().find(|alien| {
+                alien.active && Rect::new(alien.position.0, alien.position.1, alien_size.0, alien_size.1).intersects(&bullet_rect)
+            });
+
+            if let Some(alien) = hit {
+                alien.active = false;
+                bullet.active = false;
+                destroyed += 1;
+            }
+        }
+
+        destroyed
+    }
+
+    /// Both rolls are made for every pair, like `CoinManager::offer_coins`.
+    /// The alien starts halfway between the new pair and the one before it.
+    fn offer_alien(&mut self, pipe_manager: &PipeManager, pipe_id: u32, ground_y: f32) {
+        let spawn = self.rng.random_bool(f64::from(self.config.alien_chance));
+        let offset = self.rng.random_range(-1.0..=1.0_f32);
+        if !self.enabled || !spawn {
+            return;
+        }
+
+        let pipe = pipe_manager.active_pipes().find(|pipe| pipe.id == pipe_id);
+        let previous = pipe_id.checked_sub(1)
+            .and_then(|id| pipe_manager.active_pipes().find(|pipe| pipe.id == id));
+        let (Some(pipe), Some(previous)) = (pipe, previous) else {
+            return;
+        };
+
+        let size = self.config.alien_size;
+        let x = (previous.x + pipe.x + pipe_manager.pipe_width - size.0) / 2.0;
+        let middle = (previous.gap_y + pipe.gap_y) / 2.0;
+        let anchor_y = (middle + offset * self.config.alien_bob_amplitude - size.1 / 2.0).clamp(0.0, (ground_y - size.1).max(0.0));
+
+        let alien = Alien { position: (x, anchor_y), anchor_y, age: 0, active: true };
+        match self.aliens.iter_mut().find(|alien| !alien.active) {
+            Some(slot) => *slot = alien,
+            None => self.aliens.push(alien),
+        }
+    }
+
+    pub fn collides_with(&self, hitbox: &Obb) -> bool {
+        let size = self.config.alien_size;
+        self.aliens.iter().any(|alien| {
+            alien.active && hitbox.intersects(&Rect::new(alien.position.0, alien.position.1, size.0, size.1))
+        })
+    }
+
+    /// Turns the shooter on or off for the next run.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn reset(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed ^ ALIEN_SEED_SALT);
+        for bullet in &mut self.bullets {
+            bullet.active = false;
+        }
+        for alien in &mut self.aliens {
+            alien.active = false;
+        }
+        self.cooldown = 0;
+        self.next_pipe = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::difficulty::Difficulty;
+    use crate::mode::GameMode;
+    use crate::state::GameState;
+
+    const SEED: u64 = 7;
+    const CANVAS: (f32, f32) = (800.0, 600.0);
+    const GROUND_Y: f32 = 488.0;
+
+    fn pipes() -> PipeManager {
+        let config = GameConfig::default();
+        let mut pipe_manager = PipeManager::new(&config, CANVAS, SEED, GameMode::Shooter);
+        pipe_manager.set_difficulty(Difficulty::base(&config));
+        pipe_manager
+    }
+
+    fn alien_at(position: (f32, f32)) -> Alien {
+        Alien { position, anchor_y: position.1, age: 0, active: true }
+    }
+
+    /// Runs the pipes and the shooter until `pairs` pipe pairs have spawned.
+    fn run(pipe_manager: &mut PipeManager, shooter: &mut Shooter, pairs: u32) {
+        while pipe_manager.pipe_counter < pairs {
+            pipe_manager.update();
+            shooter.update(pipe_manager, CANVAS, GROUND_Y, 1.0);
+        }
+    }
+
+    #[test]
+    fn firing_waits_for_the_cooldown() {
+        let mut shooter = Shooter::new(ShooterConfig::default(), SEED, true);
+        let pipe_manager = pipes();
+        assert!(shooter.fire((100.0, 300.0)));
+        assert!(!shooter.fire((100.0, 300.0)));
+
+        let cooldown = (ShooterConfig::default().fire_cooldown * TICK_RATE) as u64;
+        for _ in 0..cooldown {
+            shooter.update(&pipe_manager, CANVAS, GROUND_Y, 1.0);
+        }
+        assert!(shooter.fire((100.0, 300.0)));
+        assert_eq!(shooter.bullets().iter().filter(|bullet| bullet.active).count(), 2);
+    }
+
+    #[test]
+    fn bullets_take_aliens_down() {
+        let config = ShooterConfig { alien_bob_amplitude: 0.0, ..ShooterConfig::default() };
+        let mut shooter = Shooter::new(config, SEED, true);
+        let pipe_manager = pipes();
+        shooter.aliens.push(alien_at((300.0, 280.0)));
+        shooter.fire((100.0, 300.0));
+
+        let mut destroyed = 0;
+        for _ in 0..60 {
+            destroyed += shooter.update(&pipe_manager, CANVAS, GROUND_Y, 1.0);
+        }
+        assert_eq!(destroyed, 1);
+        assert!(shooter.aliens().iter().all(|alien| !alien.active));
+        assert!(shooter.bullets().iter().all(|bullet| !bullet.active), "the bullet is spent on the alien");
+    }
+
+    #[test]
+    fn bullets_stop_at_pipes() {
+        let mut shooter = Shooter::new(ShooterConfig::default(), SEED, true);
+        let mut pipe_manager = pipes();
+        run(&mut pipe_manager, &mut shooter, 1);
+        let pipe = pipe_manager.active_pipes().next().unwrap().clone();
+
+        // Aimed at the top pipe, with an alien hiding behind it.
+        shooter.aliens.push(alien_at((pipe.x + 200.0, 0.0)));
+        shooter.fire((pipe.x - 100.0, pipe.gap_y - pipe.gap_size / 2.0 - 50.0));
+        let mut destroyed = 0;
+        for _ in 0..30 {
+            destroyed += shooter.update(&pipe_manager, CANVAS, GROUND_Y, 1.0);
+        }
+        assert_eq!(destroyed, 0);
+        assert!(shooter.bullets().iter().all(|bullet| !bullet.active));
+    }
+
+    #[test]
+    fn aliens_spawn_between_pairs_only_when_enabled() {
+        let config = ShooterConfig { alien_chance: 1.0, ..ShooterConfig::default() };
+
+        let mut pipe_manager = pipes();
+        let mut shooter = Shooter::new(config, SEED, true);
+        run(&mut pipe_manager, &mut shooter, 3);
+        assert_eq!(shooter.aliens().iter().filter(|alien| alien.active).count(), 2);
+
+        let mut pipe_manager = pipes();
+        let mut shooter = Shooter::new(config, SEED, false);
+        run(&mut pipe_manager, &mut shooter, 3);
+        assert!(shooter.aliens().is_empty());
+        assert!(!shooter.fire((100.0, 300.0)));
+        assert!(shooter.bullets().is_empty());
+    }
+
+    #[test]
+    fn aliens_kill_the_bird_in_shooter_mode() {
+        let config = GameConfig::default();
+        let mut state = GameState::new(&config, CANVAS, SEED, GameMode::Shooter);
+        let bird = state.bird.position;
+        state.shooter.aliens.push(alien_at(bird));
+
+        let events = state.step(false, false);
+        assert!(events.crashed);
+        assert!(state.game_over);
+    }
+
+    #[test]
+    fn other_modes_have_no_aliens() {
+        let config = GameConfig { shooter: ShooterConfig { alien_chance: 1.0, ..ShooterConfig::default() }, ..GameConfig::default() };
+        for mode in GameMode::ALL.into_iter().filter(|mode| !mode.has_aliens()) {
+            let mut state = GameState::new(&config, CANVAS, SEED, mode);
+            for _ in 0..60 * 20 {
+                // Keeps the bird in the next gap so the run doesn't end.
+                let bird = &mut state.bird;
+                if let Some(pipe) = state.pipe_manager.active_pipes()
+                    .filter(|pipe| pipe.x + state.pipe_manager.pipe_width > bird.position.0)
+                    .min_by(|a, b| a.x.total_cmp(&b.x))
+                {
+                    bird.position.1 = pipe.gap_y - bird.size.1 / 2.0;
+                }
+                bird.velocity = 0.0;
+                assert!(!state.step(false, true).fired, "{:?} fired", mode);
+            }
+            assert!(state.pipe_manager.pipe_counter > 3);
+            assert!(state.shooter.aliens().is_empty(), "{:?} spawned aliens", mode);
+        }
+    }
+}
@@ -0,0 +1,476 @@
+use crate::bird::Bird;
+use crate::collision::Obb;
+use crate::coin::CoinManager;
+use crate::config::GameConfig;
+use crate::difficulty::Difficulty;
+use crate::mode::GameMode;
+use crate::pipe::PipeManager;
+use crate::powerup::{PowerUpKind, PowerUps};
+use crate::score::ScoreManager;
+use crate::shooter::Shooter;
+
+const HOVER_AMPLITUDE: f32 = 8.0;
+const HOVER_SPEED: f32 = 0.12;
+
+/// What happened during a single `GameState::step`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StepEvents {
+    pub flapped: bool,
+    pub fired: bool,
+    pub scored: bool,
+    pub crashed: bool,
+    pub coin_collected: bool,
+    pub power_up: Option<PowerUpKind>,
+    /// The shield took a hit that would have ended the run.
+    pub shield_broken: bool,
+    pub aliens_destroyed: usize,
+    /// The mode's time limit ran out this tick.
+    pub time_up: bool,
+}
+
+/// The whole game world without any renderer attached. `Game` owns one of
+/// these and mirrors it onto the stork Canvas after every tick, but it can
+/// just as well be stepped on its own.
+#[derive(Debug)]
+pub struct GameState {
+    pub bird: Bird,
+    pub pipe_manager: PipeManager,
+    pub score_manager: ScoreManager,
+    pub coin_manager: CoinManager,
+    pub power_ups: PowerUps,
+    pub shooter: Shooter,
+    /// How far the world has scrolled since the run started.
+    pub distance: f32,
+    pub canvas_size: (f32, f32),
+    pub tick: u64,
+    pub game_over: bool,
+    pub mode: GameMode,
+    pub config: GameConfig,
+}
+
+impl GameState {
+    pub fn new(config: &GameConfig, canvas_size: (f32, f32), seed: u64, mode: GameMode) -> Self {
+        let mut state = Self {
+            bird: Self::spawn_bird(config, canvas_size),
+            pipe_manager: PipeManager::new(config, canvas_size, seed, mode),
+            score_manager: ScoreManager::new(),
+            coin_manager: CoinManager::new(config.coins, seed),
+            power_ups: PowerUps::new(config.power_ups, seed),
+            shooter: Shooter::new(config.shooter, seed, mode.has_aliens()),
+            distance: 0.0,
+            canvas_size,
+            tick: 0,
+            game_over: false,
+            mode,
+            config: config.clone(),
+        };
+        state.apply_difficulty();
+        state
+    }
+
+    fn spawn_bird(config: &GameConfig, canvas_size: (f32, f32)) -> Bird {
+        Bird::new(bird_start(config, canvas_size), config.layout.bird_size, config.physics, config.tilt)
+    }
+
+    pub fn bird_start(&self) -> (f32, f32) {
+        bird_start(&self.config, self.canvas_size)
+    }
+
+    pub fn ground_y(&self) -> f32 {
+        self.canvas_size.1 - self.config.layout.base_height
+    }
+
+    /// Advances the world by one fixed tick. `fire` only does anything in
+    /// modes with aliens.
+    pub fn step(&mut self, flap: bool, fire: bool) -> StepEvents {
+        let mut events = StepEvents::default();
+        if self.game_over {
+            return events;
+        }
+
+        self.tick += 1;
+
+        if flap {
+            self.bird.flap();
+            events.flapped = true;
+        }
+        self.bird.update();
+        if fire {
+            events.fired = self.shooter.fire(self.muzzle());
+        }
+        self.pipe_manager.set_time_scale(self.power_ups.time_scale());
+        self.pipe_manager.update();
+        self.update_pickups();
+        self.update_scroll();
+        events.scored = self.score_manager.check_score(&self.bird, self.pipe_manager.pool(), self.pipe_manager.pipe_width);
+        events.aliens_destroyed = self.shooter.update(&self.pipe_manager, self.canvas_size, self.ground_y(), self.power_ups.time_scale());
+        self.score_manager.add(events.aliens_destroyed * self.config.shooter.alien_points);
+        if events.scored || events.aliens_destroyed > 0 {
+            self.apply_difficulty();
+        }
+
+        let hitbox = self.bird_hitbox();
+        events.coin_collected = self.coin_manager.collect(&hitbox);
+        events.power_up = self.power_ups.collect(&hitbox);
+        self.check_ceiling_collision();
+
+        if !self.mode.can_die() {
+            self.check_ground_landing();
+        } else if self.check_collisions() {
+            if self.power_ups.invulnerable() {
+                self.check_ground_landing();
+            } else if self.power_ups.break_shield() {
+                events.shield_broken = true;
+                self.check_ground_landing();
+            } else {
+                self.end_run();
+                events.crashed = true;
+            }
+        }
+
+        if self.time_left() == Some(0) && !self.game_over {
+            self.end_run();
+            events.time_up = true;
+        }
+
+        events
+    }
+
+    /// Stops the run where it is. Running power-ups end with it.
+    pub fn end_run(&mut self) {
+        self.game_over = true;
+        self.power_ups.clear();
+    }
+
+    /// Moves coins and power-ups along with the pipes. A power-up takes the
+    /// place of a coin in the same gap, and the magnet pulls coins towards
+    /// the bird once they have moved.
+    fn update_pickups(&mut self) {
+        self.power_ups.update(&self.pipe_manager);
+        self.coin_manager.update(&self.pipe_manager);
+        for pipe_id in self.power_ups.occupied_gaps() {
+            self.coin_manager.clear_gap(pipe_id);
+        }
+
+        if let Some((radius, pull)) = self.power_ups.magnet() {
+            self.coin_manager.attract(self.bird.center(), radius, pull);
+        }
+    }
+
+    /// Where bullets leave the bird: the middle of its front edge.
+    fn muzzle(&self) -> (f32, f32) {
+        (self.bird.position.0 + self.bird.size.0, self.bird.position.1 + self.bird.size.1 / 2.0)
+    }
+
+    /// The bird's hitbox with shrink applied.
+    pub fn bird_hitbox(&self) -> Obb {
+        self.bird.hitbox().scaled(self.power_ups.hitbox_scale())
+    }
+
+    /// Ticks until the mode's time limit, if it has one.
+    pub fn time_left(&self) -> Option<u64> {
+        self.mode.time_limit().map(|limit| limit.saturating_sub(self.tick))
+    }
+
+    /// Bobs the bird around its start position while waiting for the first
+    /// flap. Nothing else in the world moves.
+    pub fn hover(&mut self, ticks: u64) {
+        let start = self.bird_start();
+        self.bird.position = (start.0, start.1 + (ticks as f32 * HOVER_SPEED).sin() * HOVER_AMPLITUDE);
+        self.bird.level();
+    }
+
+    /// One tick of the ready screen: the bird hovers until `go`, then goes
+    /// back to its start position. The hover is only for show, so every
+    /// run, live or replayed, sets off from the same place. Returns true
+    /// once the run starts.
+    pub fn ready(&mut self, ticks: u64, go: bool) -> bool {
+        if !go {
+            self.hover(ticks);
+            return false;
+        }
+        self.bird.position = self.bird_start();
+        self.bird.level();
+        true
+    }
+
+    /// Drops the bird after a crash with the rest of the world frozen.
+    /// Returns true once it is resting on the ground.
+    pub fn fall(&mut self) -> bool {
+        let rest_y = self.ground_y() - self.bird.size.1;
+        if self.bird.position.1 >= rest_y {
+            self.bird.position.1 = rest_y;
+            self.bird.velocity = 0.0;
+            return true;
+        }
+
+        self.bird.update();
+        self.bird.position.1 = self.bird.position.1.min(rest_y);
+        false
+    }
+
+    /// The ground scrolls with whatever speed the pipes currently have, so
+    /// the two never drift apart as the difficulty changes.
+    fn update_scroll(&mut self) {
+        self.distance -= self.pipe_manager.speed();
+    }
+
+    fn apply_difficulty(&mut self) {
+        let score = self.score_manager.score;
+        self.pipe_manager.set_difficulty(Difficulty::at(&self.config, score));
+        self.pipe_manager.update_motion(score);
+    }
+
+    fn check_ceiling_collision(&mut self) {
+        if self.bird.position.1 <= 0.0 {
+            self.bird.position.1 = 0.0;
+            self.bird.velocity = 0.0;
+        }
+    }
+
+    fn check_ground_landing(&mut self) {
+        let rest_y = self.ground_y() - self.bird.size.1;
+        if self.bird.position.1 >= rest_y {
+            self.bird.position.1 = rest_y;
+            self.bird.velocity = 0.0;
+        }
+    }
+
+    fn check_collisions(&self) -> bool {
+        let hitbox = self.bird_hitbox();
+        let bounds = hitbox.bounds();
+
+        bounds.y + bounds.h > self.ground_y()
+            || self.pipe_manager.collides_with(&hitbox)
+            || self.shooter.collides_with(&hitbox)
+    }
+
+    /// Re-lays the world out for a new canvas size. Pipes already on screen
+    /// keep their x but have their gaps pulled back into the new spawn range,
+    /// and the bird moves to the new start column without leaving the sky.
+    pub fn resize(&mut self, canvas_size: (f32, f32)) {
+        self.canvas_size = canvas_size;
+        self.pipe_manager.resize(canvas_size);
+        self.coin_manager.resize(self.pipe_manager.gap_range());
+
+        self.bird.position.0 = self.bird_start().0;
+        let max_y = (self.ground_y() - self.bird.size.1).max(0.0);
+        self.bird.position.1 = self.bird.position.1.clamp(0.0, max_y);
+    }
+
+    /// Switches the rules and starts a fresh run under them.
+    pub fn set_mode(&mut self, mode: GameMode, seed: u64) {
+        self.mode = mode;
+        self.pipe_manager.set_mode(&self.config, mode);
+        self.shooter.set_enabled(mode.has_aliens());
+        self.reset(seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.pipe_manager.seed
+    }
+
+    pub fn reset(&mut self, seed: u64) {
+        self.bird = Self::spawn_bird(&self.config, self.canvas_size);
+        self.pipe_manager.reset(seed);
+        self.score_manager.reset();
+        self.coin_manager.reset(seed);
+        self.power_ups.reset(seed);
+        self.shooter.reset(seed);
+        self.apply_difficulty();
+        self.distance = 0.0;
+        self.tick = 0;
+        self.game_over = false;
+    }
+}
+
+/// `layout.bird_start` is given for `layout.canvas_size`. Narrower canvases
+/// pull the bird left so a portrait screen still shows what's coming, and
+/// the height is kept proportional so it starts at the same spot in the sky.
+fn bird_start(config: &GameConfig, canvas_size: (f32, f32)) -> (f32, f32) {
+    let layout = &config.layout;
+    let x = layout.bird_start.0 * (canvas_size.0 / layout.canvas_size.0).min(1.0);
+    let y = layout.bird_start.1 * canvas_size.1 / layout.canvas_size.1;
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::{Replay, ReplayPlayer};
+
+    const SEED: u64 = 7;
+    const MAX_TICKS: u64 = 60 * 120;
+
+    fn new_state(mode: GameMode) -> GameState {
+        let config = GameConfig::default();
+        GameState::new(&config, config.layout.canvas_size, SEED, mode)
+    }
+
+    /// Flaps whenever the falling bird gets near the bottom of the next gap.
+    fn autopilot(state: &GameState) -> bool {
+        let bird = &state.bird;
+        let gap_bottom = state.pipe_manager.active_pipes()
+            .filter(|pipe| pipe.x + state.pipe_manager.pipe_width > bird.position.0)
+            .min_by(|a, b| a.x.total_cmp(&b.x))
+            .map(|pipe| pipe.gap_y + pipe.gap_size / 2.0)
+            .unwrap_or(state.canvas_size.1 / 2.0);
+        bird.velocity >= 0.0 && bird.position.1 + bird.size.1 > gap_bottom - 15.0
+    }
+
+    /// Plays until the run ends, returning the ticks it flapped on and the
+    /// tick it ended on.
+    fn play(state: &mut GameState, mut flap_at: impl FnMut(&GameState) -> bool) -> (Vec<u64>, Option<u64>) {
+        let mut flaps = Vec::new();
+        while state.tick < MAX_TICKS {
+            let flap = flap_at(state);
+            if flap {
+                flaps.push(state.tick);
+            }
+            if state.step(flap, false).crashed {
+                return (flaps, Some(state.tick));
+            }
+        }
+        (flaps, None)
+    }
+
+    #[test]
+    fn gravity_pulls_the_bird_down() {
+        let mut state = new_state(GameMode::Classic);
+        let start_y = state.bird.position.1;
+        state.step(false, false);
+        assert_eq!(state.bird.velocity, state.config.physics.gravity);
+        assert!(state.bird.position.1 > start_y);
+    }
+
+    #[test]
+    fn flap_sets_the_speed_however_fast_the_bird_falls() {
+        let mut state = new_state(GameMode::Classic);
+        for _ in 0..15 {
+            state.step(false, false);
+        }
+        let physics = state.config.physics;
+        let y = state.bird.position.1;
+        let events = state.step(true, false);
+        assert!(events.flapped);
+        assert_eq!(state.bird.velocity, physics.flap_impulse + physics.gravity);
+        assert!(state.bird.position.1 < y);
+    }
+
+    #[test]
+    fn fall_speed_is_capped() {
+        let mut state = new_state(GameMode::Zen);
+        for _ in 0..200 {
+            state.step(false, false);
+            assert!(state.bird.velocity <= state.config.physics.max_fall_speed);
+        }
+    }
+
+    #[test]
+    fn hitting_the_ground_ends_the_run() {
+        let mut state = new_state(GameMode::Classic);
+        let (_, crashed_at) = play(&mut state, |_| false);
+        assert!(crashed_at.is_some());
+        assert!(state.game_over);
+        assert!(state.bird_hitbox().bounds().y + state.bird_hitbox().bounds().h > state.ground_y());
+
+        let tick = state.tick;
+        assert!(!state.step(true, false).flapped);
+        assert_eq!(state.tick, tick);
+    }
+
+    #[test]
+    fn clearing_a_pipe_scores() {
+        // Zen mode can't crash, so a bird sitting on the ground sees every pipe pass.
+        let mut state = new_state(GameMode::Zen);
+        let mut scored_at = None;
+        while state.tick < MAX_TICKS && scored_at.is_none() {
+            if state.step(false, false).scored {
+                scored_at = Some(state.tick);
+            }
+        }
+        assert!(scored_at.is_some());
+        assert_eq!(state.score_manager.score, 1);
+    }
+
+    #[test]
+    fn a_shield_saves_the_bird_once() {
+        let mut state = new_state(GameMode::Classic);
+        state.power_ups.activate(PowerUpKind::Shield);
+
+        let mut broken_at = Vec::new();
+        let mut crashed_at = None;
+        while crashed_at.is_none() && state.tick < MAX_TICKS {
+            let events = state.step(false, false);
+            if events.shield_broken {
+                broken_at.push(state.tick);
+            }
+            if events.crashed {
+                crashed_at = Some(state.tick);
+            }
+        }
+
+        assert_eq!(broken_at.len(), 1);
+        let grace = (state.config.power_ups.shield_grace * 60.0) as u64;
+        assert!(crashed_at.unwrap() >= broken_at[0] + grace);
+    }
+
+    #[test]
+    fn running_power_ups_end_with_the_run() {
+        let mut state = new_state(GameMode::Classic);
+        state.power_ups.activate(PowerUpKind::SlowMotion);
+        state.step(false, false);
+        assert_eq!(state.pipe_manager.speed(), state.config.physics.world_speed * state.config.power_ups.slow_motion_scale);
+
+        state.end_run();
+        assert_eq!(state.power_ups.running().count(), 0);
+        state.reset(SEED);
+        assert_eq!(state.power_ups.running().count(), 0);
+        state.step(false, false);
+        assert_eq!(state.pipe_manager.speed(), state.config.physics.world_speed);
+    }
+
+    #[test]
+    fn replays_start_where_the_recording_did() {
+        // Recorded like the ready screen does it: a while hovering, then
+        // the flap that starts the run is applied on the first playing tick.
+        let mut recorded = new_state(GameMode::Classic);
+        let mut ticks = 0;
+        while !recorded.ready(ticks, ticks == 20) {
+            ticks += 1;
+        }
+        let mut replay = Replay::new(SEED, recorded.canvas_size, recorded.mode);
+        let mut first = true;
+        let (flaps, end) = play(&mut recorded, |state| std::mem::take(&mut first) || autopilot(state));
+        for tick in flaps {
+            replay.record_flap(tick);
+        }
+        let end = end.expect("the autopilot crashes eventually");
+        replay.record_end(end);
+
+        // Played back, the ready screen lets go on its first tick.
+        let replay = Replay::from_text(&replay.to_text()).unwrap();
+        let mut replayed = GameState::new(&GameConfig::default(), replay.canvas_size, replay.seed, replay.mode);
+        assert!(replayed.ready(0, true));
+        let mut player = ReplayPlayer::new(replay);
+        let (_, replayed_end) = play(&mut replayed, |state| player.flap_at(state.tick));
+
+        assert_eq!(replayed_end, Some(end));
+        assert_eq!(replayed.bird.position, recorded.bird.position);
+        assert_eq!(replayed.score_manager.score, recorded.score_manager.score);
+    }
+
+    #[test]
+    fn same_seed_and_flaps_play_out_the_same() {
+        let mut first = new_state(GameMode::Classic);
+        let (flaps, first_end) = play(&mut first, autopilot);
+        assert!(first.score_manager.score > 0);
+
+        let mut second = new_state(GameMode::Classic);
+        let (_, second_end) = play(&mut second, |state| flaps.contains(&state.tick));
+
+        assert_eq!(first.score_manager.score, second.score_manager.score);
+        assert_eq!(first_end, second_end);
+        assert_eq!(first.bird.position, second.bird.position);
+    }
+}
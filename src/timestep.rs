@@ -0,0 +1,44 @@
+// std::time::Instant panics on wasm32, web-time falls back to it everywhere else.
+use web_time::Instant;
+
+pub const TICK_RATE: f32 = 60.0;
+pub const TICK_DT: f32 = 1.0 / TICK_RATE;
+
+// Longest real-time gap we try to catch up on, so a stalled window
+// doesn't come back and fast-forward through several seconds of game.
+const MAX_FRAME_TIME: f32 = 0.25;
+
+#[derive(Debug)]
+pub struct FixedTimestep {
+    accumulator: f32,
+    last_instant: Option<Instant>,
+}
+
+impl FixedTimestep {
+    pub fn new() -> Self {
+        Self {
+            accumulator: 0.0,
+            last_instant: None,
+        }
+    }
+
+    /// Feeds the real time elapsed since the last call into the accumulator
+    /// and returns how many fixed ticks are now due.
+    pub fn advance(&mut self) -> u32 {
+        let now = Instant::now();
+        let elapsed = match self.last_instant {
+            Some(last) => now.duration_since(last).as_secs_f32(),
+            None => 0.0,
+        };
+        self.last_instant = Some(now);
+
+        self.accumulator += elapsed.min(MAX_FRAME_TIME);
+
+        let mut ticks = 0;
+        while self.accumulator >= TICK_DT {
+            self.accumulator -= TICK_DT;
+            ticks += 1;
+        }
+        ticks
+    }
+}
@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+use crate::savefile::{self, SaveError};
+
+const SAVE_FILE: &str = "wallet.toml";
+
+/// Coins banked over every run so far.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Wallet {
+    pub coins: u64,
+}
+
+/// The wallet together with where it is saved. Without a data directory the
+/// coins only last until the game closes.
+#[derive(Debug)]
+pub struct WalletStore {
+    pub wallet: Wallet,
+    path: Option<PathBuf>,
+}
+
+impl WalletStore {
+    pub fn open_default() -> Self {
+        Self::open(paths::data_dir().map(|dir| dir.join(SAVE_FILE)))
+    }
+
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let wallet = match &path {
+            Some(path) => savefile::load(path),
+            None => Wallet::default(),
+        };
+        Self { wallet, path }
+    }
+
+    /// Adds a run's coins and saves straight away. A failed save is
+    /// reported but the coins still count for this session.
+    pub fn deposit(&mut self, coins: usize) {
+        if coins == 0 {
+            return;
+        }
+
+        self.wallet.coins = self.wallet.coins.saturating_add(coins as u64);
+        if let Err(err) = self.save() {
+            eprintln!("{}", err);
+        }
+    }
+
+    pub fn save(&self) -> Result<(), SaveError> {
+        match &self.path {
+            Some(path) => savefile::write(path, &self.wallet),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deposits_survive_a_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SAVE_FILE);
+
+        let mut store = WalletStore::open(Some(path.clone()));
+        store.deposit(3);
+        store.deposit(0);
+        store.deposit(4);
+        assert_eq!(store.wallet.coins, 7);
+
+        assert_eq!(WalletStore::open(Some(path)).wallet.coins, 7);
+    }
+}